@@ -44,15 +44,27 @@ pub async fn websocket(
     use std::time::Duration;
 
     use actix_example::app::Count;
-    use leptos_server_signal::ServerSignal;
+    use leptos_server_signal::{ClientSignalMessage, ServerSignal};
 
-    let (res, session, _msg_stream) = actix_ws::handle(&req, stream).unwrap();
-    let mut count = ServerSignal::<Count>::new("counter", session).unwrap();
+    let (res, mut session, mut msg_stream) = actix_ws::handle(&req, stream).unwrap();
+    let mut count = ServerSignal::<Count>::new("counter").unwrap();
 
     actix_web::rt::spawn(async move {
+        let mut interval = actix_web::rt::time::interval(Duration::from_millis(100));
         loop {
-            actix_web::rt::time::sleep(Duration::from_millis(100)).await;
-            let result = count.with(|count| count.value += 1).await;
+            let result = tokio::select! {
+                message = msg_stream.recv() => match message {
+                    Some(Ok(actix_ws::Message::Text(text))) => {
+                        match serde_json::from_str::<ClientSignalMessage>(&text) {
+                            Ok(message) => count.handle_message(&mut session, &message).await.map(|_| ()),
+                            Err(_) => Ok(()),
+                        }
+                    }
+                    Some(Ok(_)) => Ok(()),
+                    _ => break,
+                },
+                _ = interval.tick() => count.with(&mut session, |count| count.value += 1).await,
+            };
             if result.is_err() {
                 break;
             }
@@ -48,14 +48,27 @@ pub async fn websocket(ws: axum::extract::WebSocketUpgrade) -> axum::response::R
 async fn handle_socket(mut socket: axum::extract::ws::WebSocket) {
     use std::time::Duration;
 
+    use axum::extract::ws::Message;
     use axum_example::app::Count;
-    use leptos_server_signal::ServerSignal;
+    use leptos_server_signal::{ClientSignalMessage, ServerSignal};
 
     let mut count = ServerSignal::<Count>::new("counter").unwrap();
+    let mut interval = tokio::time::interval(Duration::from_millis(100));
 
     loop {
-        tokio::time::sleep(Duration::from_millis(100)).await;
-        let result = count.with(&mut socket, |count| count.value += 1).await;
+        let result = tokio::select! {
+            message = socket.recv() => match message {
+                Some(Ok(Message::Text(text))) => {
+                    match serde_json::from_str::<ClientSignalMessage>(&text) {
+                        Ok(message) => count.handle_message(&mut socket, &message).await.map(|_| ()),
+                        Err(_) => Ok(()),
+                    }
+                }
+                Some(Ok(_)) => Ok(()),
+                _ => break,
+            },
+            _ = interval.tick() => count.with(&mut socket, |count| count.value += 1).await,
+        };
         if result.is_err() {
             break;
         }
@@ -0,0 +1,53 @@
+use std::time::Duration;
+
+use futures::StreamExt;
+use leptos_server_signal::{ClientSignalMessage, Frame, ServerSignal};
+use poem::web::websocket::{WebSocket, WebSocketStream};
+use poem::{get, handler, listener::TcpListener, IntoResponse, Route, Server};
+use serde::{Deserialize, Serialize};
+
+/// The same signal as the app in the axum example, which can be used as the client.
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct Count {
+    pub value: i32,
+}
+
+#[tokio::main]
+async fn main() -> Result<(), std::io::Error> {
+    let app = Route::new().at("/ws", get(websocket));
+
+    Server::new(TcpListener::bind("127.0.0.1:3000"))
+        .run(app)
+        .await
+}
+
+#[handler]
+fn websocket(ws: WebSocket) -> impl IntoResponse {
+    ws.on_upgrade(handle_socket)
+}
+
+async fn handle_socket(mut socket: WebSocketStream) {
+    let mut count = ServerSignal::<Count>::new("counter").unwrap();
+    let mut interval = tokio::time::interval(Duration::from_millis(100));
+
+    loop {
+        let result = tokio::select! {
+            message = socket.next() => match message {
+                Some(Ok(message)) => match Frame::try_from(message) {
+                    Ok(Frame::Text(text)) => {
+                        match serde_json::from_str::<ClientSignalMessage>(&text) {
+                            Ok(message) => count.handle_message(&mut socket, &message).await.map(|_| ()),
+                            Err(_) => Ok(()),
+                        }
+                    }
+                    _ => Ok(()),
+                },
+                _ => break,
+            },
+            _ = interval.tick() => count.with(&mut socket, |count| count.value += 1).await,
+        };
+        if result.is_err() {
+            break;
+        }
+    }
+}
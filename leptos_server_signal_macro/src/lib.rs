@@ -0,0 +1,242 @@
+//! Derive macros for [leptos_server_signal](https://crates.io/crates/leptos_server_signal).
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::meta::ParseNestedMeta;
+use syn::{parse_macro_input, Attribute, Data, DeriveInput, Fields, LitStr};
+
+/// Derives `Diff`, generating json patch operations for changed fields only.
+///
+/// Fields are compared with `PartialEq`, and each changed field is sent as a single operation
+/// containing its new value. Fields marked `#[diff(nested)]` are diffed recursively,
+/// and their type must also implement `Diff`.
+///
+/// Fields renamed or skipped with `#[serde(rename = "...")]` and `#[serde(skip)]` are supported.
+/// Other serde attributes which change the json layout, such as `flatten` or `rename_all`,
+/// or which serialize the value differently, such as `with` or `into`, are not.
+#[proc_macro_derive(Diff, attributes(diff))]
+pub fn derive_diff(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand_diff(input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+fn expand_diff(input: DeriveInput) -> syn::Result<TokenStream2> {
+    reject_serde_attrs(
+        &input.attrs,
+        &[
+            "rename_all",
+            "tag",
+            "content",
+            "untagged",
+            "transparent",
+            "into",
+            "remote",
+        ],
+    )?;
+
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => &fields.named,
+            _ => {
+                return Err(syn::Error::new_spanned(
+                    &input.ident,
+                    "Diff can only be derived for structs with named fields",
+                ))
+            }
+        },
+        _ => {
+            return Err(syn::Error::new_spanned(
+                &input.ident,
+                "Diff can only be derived for structs",
+            ))
+        }
+    };
+
+    let mut ops = Vec::new();
+    for field in fields {
+        reject_serde_attrs(
+            &field.attrs,
+            &["flatten", "skip_serializing_if", "with", "serialize_with"],
+        )?;
+        let attrs = FieldAttrs::parse(&field.attrs)?;
+        if attrs.skip {
+            continue;
+        }
+        let ident = field.ident.as_ref().unwrap();
+        let name = attrs.rename.unwrap_or_else(|| ident.to_string());
+        ops.push(if attrs.nested {
+            quote! {
+                ::leptos_server_signal::Diff::diff(
+                    &self.#ident,
+                    &old.#ident,
+                    &::leptos_server_signal::__private::join(path, #name),
+                    patch,
+                )?;
+            }
+        } else {
+            quote! {
+                if self.#ident != old.#ident {
+                    ::leptos_server_signal::__private::add(patch, path, #name, &self.#ident)?;
+                }
+            }
+        });
+    }
+
+    let ident = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics ::leptos_server_signal::Diff for #ident #ty_generics #where_clause {
+            fn diff(
+                &self,
+                old: &Self,
+                path: &str,
+                patch: &mut ::leptos_server_signal::__private::Patch,
+            ) -> ::std::result::Result<(), ::leptos_server_signal::__private::serde_json::Error> {
+                #(#ops)*
+                ::std::result::Result::Ok(())
+            }
+        }
+    })
+}
+
+#[derive(Default)]
+struct FieldAttrs {
+    nested: bool,
+    skip: bool,
+    rename: Option<String>,
+}
+
+impl FieldAttrs {
+    fn parse(attrs: &[Attribute]) -> syn::Result<Self> {
+        let mut field_attrs = FieldAttrs::default();
+        for attr in attrs {
+            if attr.path().is_ident("diff") {
+                attr.parse_nested_meta(|meta| {
+                    if meta.path.is_ident("nested") {
+                        field_attrs.nested = true;
+                        Ok(())
+                    } else {
+                        Err(meta.error("unknown diff attribute"))
+                    }
+                })?;
+            } else if attr.path().is_ident("serde") {
+                attr.parse_nested_meta(|meta| {
+                    if meta.path.is_ident("skip") || meta.path.is_ident("skip_serializing") {
+                        field_attrs.skip = true;
+                    } else if meta.path.is_ident("rename") {
+                        if !meta.input.peek(syn::Token![=]) {
+                            return Err(meta.error("only `rename = \"...\"` is supported by Diff"));
+                        }
+                        field_attrs.rename = Some(meta.value()?.parse::<LitStr>()?.value());
+                    } else {
+                        skip_meta(&meta)?;
+                    }
+                    Ok(())
+                })?;
+            }
+        }
+        Ok(field_attrs)
+    }
+}
+
+/// Returns an error if a serde attribute not supported by the derive is used.
+fn reject_serde_attrs(attrs: &[Attribute], unsupported: &[&str]) -> syn::Result<()> {
+    for attr in attrs.iter().filter(|attr| attr.path().is_ident("serde")) {
+        attr.parse_nested_meta(|meta| {
+            if let Some(name) = unsupported.iter().find(|name| meta.path.is_ident(name)) {
+                return Err(meta.error(format!("serde `{name}` is not supported by Diff")));
+            }
+            skip_meta(&meta)
+        })?;
+    }
+    Ok(())
+}
+
+/// Skips the value or nested list of a serde attribute, such as `default = "..."` or `rename(serialize = "...")`.
+fn skip_meta(meta: &ParseNestedMeta) -> syn::Result<()> {
+    if meta.input.peek(syn::Token![=]) {
+        meta.value()?.parse::<syn::Expr>()?;
+    } else if meta.input.peek(syn::token::Paren) {
+        meta.parse_nested_meta(|nested| skip_meta(&nested))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use syn::parse_quote;
+
+    use super::*;
+
+    fn expand_err(input: DeriveInput) -> String {
+        expand_diff(input).unwrap_err().to_string()
+    }
+
+    #[test]
+    fn supports_rename_and_skip() {
+        let output = expand_diff(parse_quote! {
+            struct State {
+                #[serde(rename = "total")]
+                count: i32,
+                #[serde(skip)]
+                cache: Vec<i32>,
+                #[serde(default)]
+                label: String,
+            }
+        })
+        .unwrap()
+        .to_string();
+        assert!(output.contains("\"total\""));
+        assert!(!output.contains("cache"));
+        assert!(output.contains("\"label\""));
+    }
+
+    #[test]
+    fn rejects_field_serialized_differently() {
+        for attr in [
+            quote! { #[serde(with = "custom")] },
+            quote! { #[serde(serialize_with = "custom::serialize")] },
+            quote! { #[serde(flatten)] },
+            quote! { #[serde(skip_serializing_if = "Option::is_none")] },
+        ] {
+            let input = parse_quote! {
+                struct State {
+                    #attr
+                    count: i32,
+                }
+            };
+            assert!(expand_err(input).contains("is not supported by Diff"));
+        }
+    }
+
+    #[test]
+    fn rejects_container_serialized_differently() {
+        for attr in [
+            quote! { #[serde(into = "Other")] },
+            quote! { #[serde(remote = "Other")] },
+            quote! { #[serde(rename_all = "camelCase")] },
+            quote! { #[serde(transparent)] },
+        ] {
+            let input = parse_quote! {
+                #attr
+                struct State {
+                    count: i32,
+                }
+            };
+            assert!(expand_err(input).contains("is not supported by Diff"));
+        }
+    }
+
+    #[test]
+    fn rejects_non_struct() {
+        let input = parse_quote! {
+            enum State {
+                A,
+            }
+        };
+        assert!(expand_err(input).contains("only be derived for structs"));
+    }
+}
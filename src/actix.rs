@@ -1,106 +1,48 @@
-use std::borrow::Cow;
-use std::{fmt, ops};
+use std::convert::Infallible;
 
-use actix_ws::Session;
-use serde::Serialize;
-use serde_json::Value;
-use thiserror::Error;
+use actix_web::body::BoxBody;
+use actix_web::http::header;
+use actix_web::web::Bytes;
+use actix_web::{HttpRequest, HttpResponse, Responder};
+use actix_ws::{Closed, Message, Session};
+use futures::stream::{Stream, StreamExt};
 
-use crate::ServerSignalUpdate;
+use crate::{EventStream, Frame, Transport};
 
-/// A signal owned by the server which writes to the websocket when mutated.
-#[derive(Clone)]
-pub struct ServerSignal<T> {
-    name: Cow<'static, str>,
-    value: T,
-    json_value: Value,
-    session: Session,
-}
+impl Transport for Session {
+    type Error = Closed;
 
-impl<T> ServerSignal<T> {
-    /// Creates a new [`ServerSignal`], initializing `T` to default.
-    ///
-    /// This function can fail if serilization of `T` fails.
-    pub fn new(
-        name: impl Into<Cow<'static, str>>,
-        session: Session,
-    ) -> Result<Self, serde_json::Error>
-    where
-        T: Default + Serialize,
-    {
-        Ok(ServerSignal {
-            name: name.into(),
-            value: T::default(),
-            json_value: serde_json::to_value(T::default())?,
-            session,
-        })
+    async fn send_frame(&mut self, frame: Frame) -> Result<(), Self::Error> {
+        match frame {
+            Frame::Text(text) => self.text(text).await,
+            Frame::Binary(bytes) => self.binary(bytes).await,
+        }
     }
+}
 
-    /// Modifies the signal in a closure, and sends the json diffs through the websocket connection after modifying.
-    ///
-    /// # Example
-    ///
-    /// ```ignore
-    /// let count = ServerSignal::new("counter", websocket).unwrap();
-    /// count.with(|count| {
-    ///     count.value += 1;
-    /// }).await?;
-    /// ```
-    pub async fn with<O>(&mut self, f: impl FnOnce(&mut T) -> O) -> Result<O, Error>
-    where
-        T: Clone + Serialize + 'static,
-    {
-        let output = f(&mut self.value);
-        let new_json = serde_json::to_value(self.value.clone())?;
-        let update =
-            ServerSignalUpdate::new_from_json::<T>(self.name.clone(), &self.json_value, &new_json);
-        let update_json = serde_json::to_string(&update)?;
-        self.session.text(update_json).await?;
-        self.json_value = new_json;
-        Ok(output)
-    }
+impl TryFrom<Message> for Frame {
+    type Error = Message;
 
-    /// Consumes the [`ServerSignal`], returning the inner value.
-    pub fn into_value(self) -> T {
-        self.value
-    }
-
-    /// Consumes the [`ServerSignal`], returning the inner json value.
-    pub fn into_json_value(self) -> Value {
-        self.json_value
+    /// Converts a text or binary message into a frame, returning control messages as an error.
+    fn try_from(message: Message) -> Result<Self, Self::Error> {
+        match message {
+            Message::Text(text) => Ok(Frame::Text(text.to_string())),
+            Message::Binary(bytes) => Ok(Frame::Binary(bytes.to_vec())),
+            message => Err(message),
+        }
     }
 }
 
-impl<T> fmt::Debug for ServerSignal<T>
+impl<S> Responder for EventStream<S>
 where
-    T: fmt::Debug,
+    S: Stream<Item = Frame> + Unpin + 'static,
 {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "ServerSignal({:?})", self.value)
-    }
-}
+    type Body = BoxBody;
 
-impl<T> ops::Deref for ServerSignal<T> {
-    type Target = T;
-
-    fn deref(&self) -> &Self::Target {
-        &self.value
-    }
-}
-
-impl<T> AsRef<T> for ServerSignal<T> {
-    fn as_ref(&self) -> &T {
-        &self.value
+    fn respond_to(self, _req: &HttpRequest) -> HttpResponse<Self::Body> {
+        HttpResponse::Ok()
+            .content_type("text/event-stream")
+            .insert_header((header::CACHE_CONTROL, "no-cache"))
+            .streaming(self.map(|event| Ok::<_, Infallible>(Bytes::from(event))))
     }
 }
-
-/// A server signal error.
-#[derive(Debug, Error)]
-pub enum Error {
-    /// Serialization of the signal value failed.
-    #[error(transparent)]
-    SerializationFailed(#[from] serde_json::Error),
-    /// The websocket was closed.
-    #[error(transparent)]
-    WebSocket(#[from] actix_ws::Closed),
-}
@@ -1,103 +1,64 @@
-use std::borrow::Cow;
-use std::ops;
+use std::convert::Infallible;
 
-use axum::extract::ws::Message;
-use futures::sink::{Sink, SinkExt};
-use serde::Serialize;
-use serde_json::Value;
-use thiserror::Error;
+use axum::body::Body;
+use axum::extract::ws::{Message, WebSocket};
+use axum::http::header;
+use axum::response::{IntoResponse, Response};
+use futures::sink::SinkExt;
+use futures::stream::{SplitSink, Stream, StreamExt};
 
-use crate::ServerSignalUpdate;
+use crate::{EventStream, Frame, Transport};
 
-/// A signal owned by the server which writes to the websocket when mutated.
-#[derive(Clone, Debug)]
-pub struct ServerSignal<T> {
-    name: Cow<'static, str>,
-    value: T,
-    json_value: Value,
-}
+impl Transport for WebSocket {
+    type Error = axum::Error;
 
-impl<T> ServerSignal<T> {
-    /// Creates a new [`ServerSignal`], initializing `T` to default.
-    ///
-    /// This function can fail if serilization of `T` fails.
-    pub fn new(name: impl Into<Cow<'static, str>>) -> Result<Self, serde_json::Error>
-    where
-        T: Default + Serialize,
-    {
-        Ok(ServerSignal {
-            name: name.into(),
-            value: T::default(),
-            json_value: serde_json::to_value(T::default())?,
-        })
+    async fn send_frame(&mut self, frame: Frame) -> Result<(), Self::Error> {
+        self.send(frame.into()).await
     }
+}
 
-    /// Modifies the signal in a closure, and sends the json diffs through the websocket connection after modifying.
-    ///
-    /// The same websocket connection should be used for a given client, otherwise the signal could become out of sync.
-    ///
-    /// # Example
-    ///
-    /// ```ignore
-    /// let count = ServerSignal::new("counter").unwrap();
-    /// count.with(&mut websocket, |count| {
-    ///     count.value += 1;
-    /// }).await?;
-    /// ```
-    pub async fn with<O, S>(
-        &mut self,
-        sink: &mut S,
-        f: impl FnOnce(&mut T) -> O,
-    ) -> Result<O, Error>
-    where
-        T: Clone + Serialize + 'static,
-        S: Sink<Message> + Unpin,
-        axum::Error: From<<S as Sink<Message>>::Error>,
-    {
-        let output = f(&mut self.value);
-        let new_json = serde_json::to_value(self.value.clone())?;
-        let update =
-            ServerSignalUpdate::new_from_json::<T>(self.name.clone(), &self.json_value, &new_json);
-        let update_json = serde_json::to_string(&update)?;
-        sink.send(Message::Text(update_json.into()))
-            .await
-            .map_err(|err| Error::WebSocket(err.into()))?;
-        self.json_value = new_json;
-        Ok(output)
-    }
+impl Transport for SplitSink<WebSocket, Message> {
+    type Error = axum::Error;
 
-    /// Consumes the [`ServerSignal`], returning the inner value.
-    pub fn into_value(self) -> T {
-        self.value
+    async fn send_frame(&mut self, frame: Frame) -> Result<(), Self::Error> {
+        self.send(frame.into()).await
     }
+}
 
-    /// Consumes the [`ServerSignal`], returning the inner json value.
-    pub fn into_json_value(self) -> Value {
-        self.json_value
+impl From<Frame> for Message {
+    fn from(frame: Frame) -> Self {
+        match frame {
+            Frame::Text(text) => Message::Text(text.into()),
+            Frame::Binary(bytes) => Message::Binary(bytes.into()),
+        }
     }
 }
 
-impl<T> ops::Deref for ServerSignal<T> {
-    type Target = T;
+impl TryFrom<Message> for Frame {
+    type Error = Message;
 
-    fn deref(&self) -> &Self::Target {
-        &self.value
+    /// Converts a text or binary message into a frame, returning control messages as an error.
+    fn try_from(message: Message) -> Result<Self, Self::Error> {
+        match message {
+            Message::Text(text) => Ok(Frame::Text(text.to_string())),
+            Message::Binary(bytes) => Ok(Frame::Binary(bytes.to_vec())),
+            message => Err(message),
+        }
     }
 }
 
-impl<T> AsRef<T> for ServerSignal<T> {
-    fn as_ref(&self) -> &T {
-        &self.value
+impl<S> IntoResponse for EventStream<S>
+where
+    S: Stream<Item = Frame> + Unpin + Send + 'static,
+{
+    fn into_response(self) -> Response {
+        (
+            [
+                (header::CONTENT_TYPE, "text/event-stream"),
+                (header::CACHE_CONTROL, "no-cache"),
+            ],
+            Body::from_stream(self.map(Ok::<_, Infallible>)),
+        )
+            .into_response()
     }
 }
-
-/// A server signal error.
-#[derive(Debug, Error)]
-pub enum Error {
-    /// Serialization of the signal value failed.
-    #[error(transparent)]
-    SerializationFailed(#[from] serde_json::Error),
-    /// The websocket returned an error.
-    #[error(transparent)]
-    WebSocket(#[from] axum::Error),
-}
@@ -0,0 +1,341 @@
+use std::error::Error as StdError;
+use std::fmt;
+use std::sync::Arc;
+
+use crate::{ClientSignalMessage, ServerSignalMessage};
+
+/// Websocket subprotocols for the codecs enabled by cargo features, in order of preference.
+///
+/// The client offers these protocols when connecting, and the server should select one
+/// of them with [`negotiate_codec`].
+pub const PROTOCOLS: &[&str] = &[
+    #[cfg(feature = "msgpack")]
+    MessagePackCodec::PROTOCOL,
+    JsonCodec::PROTOCOL,
+];
+
+/// A websocket frame produced or consumed by a [`Codec`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Frame {
+    /// A text frame.
+    Text(String),
+    /// A binary frame.
+    Binary(Vec<u8>),
+}
+
+impl Frame {
+    /// Returns the length of the frame's payload in bytes.
+    pub fn len(&self) -> usize {
+        match self {
+            Frame::Text(text) => text.len(),
+            Frame::Binary(bytes) => bytes.len(),
+        }
+    }
+
+    /// Returns `true` if the frame's payload is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// Encodes and decodes server signal messages into websocket frames.
+///
+/// The codec used for a connection is negotiated through the websocket subprotocol,
+/// see [`PROTOCOLS`] and [`negotiate_codec`].
+pub trait Codec: fmt::Debug + Send + Sync + 'static {
+    /// Returns the websocket subprotocol which selects this codec.
+    fn protocol(&self) -> &'static str;
+
+    /// Encodes a message sent from the server to the client.
+    fn encode_server(&self, message: &ServerSignalMessage) -> Result<Frame, CodecError>;
+
+    /// Decodes a message sent from the server to the client.
+    fn decode_server(&self, frame: &Frame) -> Result<ServerSignalMessage, CodecError>;
+
+    /// Encodes a message sent from the client to the server.
+    fn encode_client(&self, message: &ClientSignalMessage) -> Result<Frame, CodecError>;
+
+    /// Decodes a message sent from the client to the server.
+    fn decode_client(&self, frame: &Frame) -> Result<ClientSignalMessage, CodecError>;
+}
+
+/// Returns the codec for a negotiated websocket subprotocol.
+///
+/// If no protocol was negotiated, or the protocol is not recognised, [`JsonCodec`] is used.
+///
+/// # Example
+///
+/// ```ignore
+/// pub async fn websocket(ws: WebSocketUpgrade) -> Response {
+///     ws.protocols(leptos_server_signal::PROTOCOLS)
+///         .on_upgrade(|socket| {
+///             let protocol = socket.protocol().and_then(|protocol| protocol.to_str().ok());
+///             let codec = leptos_server_signal::negotiate_codec(protocol);
+///             handle_socket(socket, codec)
+///         })
+/// }
+/// ```
+pub fn negotiate_codec(protocol: Option<&str>) -> Arc<dyn Codec> {
+    match protocol {
+        #[cfg(feature = "msgpack")]
+        Some(MessagePackCodec::PROTOCOL) => Arc::new(MessagePackCodec),
+        _ => Arc::new(JsonCodec),
+    }
+}
+
+/// Encodes messages as json text frames.
+///
+/// This is the default codec, and is used when no protocol is negotiated.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct JsonCodec;
+
+impl JsonCodec {
+    /// The websocket subprotocol which selects this codec.
+    pub const PROTOCOL: &'static str = "leptos-server-signal.json";
+}
+
+impl Codec for JsonCodec {
+    fn protocol(&self) -> &'static str {
+        JsonCodec::PROTOCOL
+    }
+
+    fn encode_server(&self, message: &ServerSignalMessage) -> Result<Frame, CodecError> {
+        Ok(Frame::Text(serde_json::to_string(message)?))
+    }
+
+    fn decode_server(&self, frame: &Frame) -> Result<ServerSignalMessage, CodecError> {
+        match frame {
+            Frame::Text(text) => Ok(serde_json::from_str(text)?),
+            Frame::Binary(bytes) => Ok(serde_json::from_slice(bytes)?),
+        }
+    }
+
+    fn encode_client(&self, message: &ClientSignalMessage) -> Result<Frame, CodecError> {
+        Ok(Frame::Text(serde_json::to_string(message)?))
+    }
+
+    fn decode_client(&self, frame: &Frame) -> Result<ClientSignalMessage, CodecError> {
+        match frame {
+            Frame::Text(text) => Ok(serde_json::from_str(text)?),
+            Frame::Binary(bytes) => Ok(serde_json::from_slice(bytes)?),
+        }
+    }
+}
+
+/// Encodes messages as [MessagePack](https://msgpack.org) binary frames.
+#[cfg(feature = "msgpack")]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct MessagePackCodec;
+
+#[cfg(feature = "msgpack")]
+impl MessagePackCodec {
+    /// The websocket subprotocol which selects this codec.
+    pub const PROTOCOL: &'static str = "leptos-server-signal.msgpack";
+
+    fn decode<T>(frame: &Frame) -> Result<T, CodecError>
+    where
+        T: for<'de> serde::Deserialize<'de>,
+    {
+        match frame {
+            Frame::Binary(bytes) => Ok(rmp_serde::from_slice(bytes)?),
+            Frame::Text(_) => Err(CodecError::new("expected a binary frame")),
+        }
+    }
+}
+
+#[cfg(feature = "msgpack")]
+impl Codec for MessagePackCodec {
+    fn protocol(&self) -> &'static str {
+        MessagePackCodec::PROTOCOL
+    }
+
+    fn encode_server(&self, message: &ServerSignalMessage) -> Result<Frame, CodecError> {
+        // Structs are encoded as maps, as internally tagged enums cannot be decoded from arrays
+        Ok(Frame::Binary(rmp_serde::to_vec_named(message)?))
+    }
+
+    fn decode_server(&self, frame: &Frame) -> Result<ServerSignalMessage, CodecError> {
+        MessagePackCodec::decode(frame)
+    }
+
+    fn encode_client(&self, message: &ClientSignalMessage) -> Result<Frame, CodecError> {
+        Ok(Frame::Binary(rmp_serde::to_vec_named(message)?))
+    }
+
+    fn decode_client(&self, frame: &Frame) -> Result<ClientSignalMessage, CodecError> {
+        MessagePackCodec::decode(frame)
+    }
+}
+
+/// An error encoding or decoding a frame.
+#[derive(Debug)]
+pub struct CodecError(Box<dyn StdError + Send + Sync>);
+
+impl CodecError {
+    /// Creates a new [`CodecError`] from any error.
+    pub fn new(err: impl Into<Box<dyn StdError + Send + Sync>>) -> Self {
+        CodecError(err.into())
+    }
+}
+
+impl fmt::Display for CodecError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl StdError for CodecError {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        self.0.source()
+    }
+}
+
+impl From<serde_json::Error> for CodecError {
+    fn from(err: serde_json::Error) -> Self {
+        CodecError::new(err)
+    }
+}
+
+#[cfg(feature = "msgpack")]
+impl From<rmp_serde::encode::Error> for CodecError {
+    fn from(err: rmp_serde::encode::Error) -> Self {
+        CodecError::new(err)
+    }
+}
+
+#[cfg(feature = "msgpack")]
+impl From<rmp_serde::decode::Error> for CodecError {
+    fn from(err: rmp_serde::decode::Error) -> Self {
+        CodecError::new(err)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use json_patch::Patch;
+    use serde_json::json;
+
+    use super::*;
+    use crate::{ServerSignalBatch, ServerSignalSnapshot, ServerSignalUpdate};
+
+    /// Returns an update using every kind of patch operation.
+    fn update() -> ServerSignalUpdate {
+        let patch: Patch = serde_json::from_value(json!([
+            { "op": "add", "path": "/items/-", "value": { "id": 1, "tags": ["a", "b"] } },
+            { "op": "remove", "path": "/old" },
+            { "op": "replace", "path": "/count", "value": -3 },
+            { "op": "move", "from": "/a", "path": "/b" },
+            { "op": "copy", "from": "/b", "path": "/c" },
+            { "op": "test", "path": "/ratio", "value": 0.5 },
+            { "op": "add", "path": "/escaped~1key~0", "value": null },
+        ]))
+        .unwrap();
+        ServerSignalUpdate::new_from_patch("count", patch).with_version(7)
+    }
+
+    fn server_messages() -> Vec<ServerSignalMessage> {
+        let snapshot = ServerSignalSnapshot::new_from_json(
+            "count",
+            json!({
+                "value": u64::MAX,
+                "negative": i64::MIN,
+                "ratio": 1.5,
+                "name": "ari",
+                "items": [true, null, { "nested": [] }],
+            }),
+        )
+        .with_version(3);
+        let mut batch = ServerSignalBatch::new();
+        batch.push(update());
+        batch.push(snapshot.clone());
+        vec![update().into(), snapshot.into(), batch.into()]
+    }
+
+    fn client_messages() -> Vec<ClientSignalMessage> {
+        vec![
+            ClientSignalMessage::Resync {
+                name: "count".into(),
+            },
+            ClientSignalMessage::Update(update()),
+            ClientSignalMessage::Subscribe {
+                name: "count".into(),
+            },
+            ClientSignalMessage::Unsubscribe {
+                name: "count".into(),
+            },
+        ]
+    }
+
+    fn assert_round_trips(codec: &dyn Codec) {
+        for message in server_messages() {
+            let frame = codec.encode_server(&message).unwrap();
+            assert_eq!(codec.decode_server(&frame).unwrap(), message);
+        }
+        for message in client_messages() {
+            let frame = codec.encode_client(&message).unwrap();
+            assert_eq!(codec.decode_client(&frame).unwrap(), message);
+        }
+    }
+
+    #[test]
+    fn json_codec_round_trips_every_message() {
+        assert_round_trips(&JsonCodec);
+    }
+
+    #[test]
+    fn json_codec_encodes_text_and_decodes_binary() {
+        let message = ServerSignalMessage::from(update());
+        let Frame::Text(text) = JsonCodec.encode_server(&message).unwrap() else {
+            panic!("expected a text frame");
+        };
+        let frame = Frame::Binary(text.into_bytes());
+        assert_eq!(JsonCodec.decode_server(&frame).unwrap(), message);
+    }
+
+    #[cfg(feature = "msgpack")]
+    #[test]
+    fn msgpack_codec_round_trips_every_message() {
+        assert_round_trips(&MessagePackCodec);
+    }
+
+    #[cfg(feature = "msgpack")]
+    #[test]
+    fn msgpack_codec_rejects_text_frames() {
+        let message = ServerSignalMessage::from(update());
+        assert!(matches!(
+            MessagePackCodec.encode_server(&message).unwrap(),
+            Frame::Binary(_)
+        ));
+
+        let frame = JsonCodec.encode_server(&message).unwrap();
+        assert!(MessagePackCodec.decode_server(&frame).is_err());
+        let frame = JsonCodec
+            .encode_client(&ClientSignalMessage::Update(update()))
+            .unwrap();
+        assert!(MessagePackCodec.decode_client(&frame).is_err());
+    }
+
+    #[test]
+    fn negotiate_codec_falls_back_to_json() {
+        assert_eq!(negotiate_codec(None).protocol(), JsonCodec::PROTOCOL);
+        assert_eq!(
+            negotiate_codec(Some("unknown")).protocol(),
+            JsonCodec::PROTOCOL
+        );
+        assert_eq!(
+            negotiate_codec(Some(JsonCodec::PROTOCOL)).protocol(),
+            JsonCodec::PROTOCOL
+        );
+        assert_eq!(PROTOCOLS.last(), Some(&JsonCodec::PROTOCOL));
+    }
+
+    #[cfg(feature = "msgpack")]
+    #[test]
+    fn negotiate_codec_selects_msgpack() {
+        assert_eq!(
+            negotiate_codec(Some(MessagePackCodec::PROTOCOL)).protocol(),
+            MessagePackCodec::PROTOCOL
+        );
+        assert_eq!(PROTOCOLS.first(), Some(&MessagePackCodec::PROTOCOL));
+    }
+}
@@ -0,0 +1,524 @@
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::ops;
+
+use json_patch::jsonptr::PointerBuf;
+use json_patch::{AddOperation, Patch, PatchOperation, RemoveOperation};
+use serde::ser::SerializeMap;
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::strategy::nest;
+use crate::{Error, ServerSignal, Transport};
+
+/// A server signal containing a list of items identified by a key,
+/// which sends a single patch operation per inserted or removed item.
+///
+/// Each item is identified by the key returned by `key`, which should be unique within the list.
+/// Updating an item only diffs that item, so inserting at the start of the list
+/// does not rewrite every item after it.
+///
+/// The signal can be read as a `Vec<T>`, and the inner [`ServerSignal`] is available through `Deref`,
+/// for handling messages and sending snapshots.
+/// On the client, use [`create_server_vec`](crate::create_server_vec) for a signal per item.
+///
+/// # Example
+///
+/// ```ignore
+/// let mut todos = ServerVec::new("todos", |todo: &Todo| todo.id).unwrap();
+/// todos.insert(&mut websocket, 0, Todo { id: 3, title: "New".to_string(), done: false }).await?;
+/// todos.update(&mut websocket, &3, |todo| todo.done = true).await?;
+/// todos.remove(&mut websocket, &3).await?;
+/// ```
+#[derive(Clone, Debug)]
+pub struct ServerVec<T, K> {
+    signal: ServerSignal<Vec<T>>,
+    key: fn(&T) -> K,
+}
+
+impl<T, K> ServerVec<T, K>
+where
+    K: PartialEq,
+{
+    /// Creates a new empty [`ServerVec`], identifying items by the key returned by `key`.
+    ///
+    /// This function can fail if serialization of an empty list fails.
+    pub fn new(
+        name: impl Into<Cow<'static, str>>,
+        key: fn(&T) -> K,
+    ) -> Result<Self, serde_json::Error>
+    where
+        T: Serialize,
+    {
+        Ok(ServerVec::from_signal(ServerSignal::new(name)?, key))
+    }
+
+    /// Creates a new [`ServerVec`] from a configured [`ServerSignal`].
+    pub fn from_signal(signal: ServerSignal<Vec<T>>, key: fn(&T) -> K) -> Self {
+        ServerVec { signal, key }
+    }
+
+    /// Returns the index of the item with a key.
+    pub fn position(&self, key: &K) -> Option<usize> {
+        self.signal.iter().position(|item| (self.key)(item) == *key)
+    }
+
+    /// Returns the item with a key.
+    pub fn get(&self, key: &K) -> Option<&T> {
+        self.signal.iter().find(|item| (self.key)(item) == *key)
+    }
+
+    /// Appends an item to the end of the list.
+    pub async fn push<W>(&mut self, transport: &mut W, item: T) -> Result<(), Error>
+    where
+        T: Serialize,
+        W: Transport,
+    {
+        let index = self.signal.len();
+        self.insert(transport, index, item).await
+    }
+
+    /// Inserts an item at `index`, sending it as a single `add` operation.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is greater than the length of the list.
+    pub async fn insert<W>(&mut self, transport: &mut W, index: usize, item: T) -> Result<(), Error>
+    where
+        T: Serialize,
+        W: Transport,
+    {
+        self.signal
+            .with_patch(transport, |items| {
+                let patch = Patch(vec![PatchOperation::Add(AddOperation {
+                    path: pointer(index),
+                    value: serde_json::to_value(&item)?,
+                })]);
+                items.insert(index, item);
+                Ok(((), patch))
+            })
+            .await
+    }
+
+    /// Removes the item with a key, sending a single `remove` operation.
+    ///
+    /// Returns the removed item, or `None` if there is no item with the key.
+    pub async fn remove<W>(&mut self, transport: &mut W, key: &K) -> Result<Option<T>, Error>
+    where
+        T: Serialize,
+        W: Transport,
+    {
+        let Some(index) = self.position(key) else {
+            return Ok(None);
+        };
+        self.signal
+            .with_patch(transport, |items| {
+                let patch = Patch(vec![PatchOperation::Remove(RemoveOperation {
+                    path: pointer(index),
+                })]);
+                Ok((Some(items.remove(index)), patch))
+            })
+            .await
+    }
+
+    /// Modifies the item with a key in a closure, sending the diff of that item only.
+    ///
+    /// Returns the output of the closure, or `None` if there is no item with the key.
+    pub async fn update<O, W>(
+        &mut self,
+        transport: &mut W,
+        key: &K,
+        f: impl FnOnce(&mut T) -> O,
+    ) -> Result<Option<O>, Error>
+    where
+        T: Serialize,
+        W: Transport,
+    {
+        let Some(index) = self.position(key) else {
+            return Ok(None);
+        };
+        let strategy = self.signal.strategy().clone();
+        self.signal
+            .with_patch(transport, |items| {
+                let item = &mut items[index];
+                let old_json = serde_json::to_value(&*item)?;
+                let output = f(item);
+                let new_json = serde_json::to_value(&*item)?;
+                let patch = nest(strategy.diff(&old_json, &new_json), index);
+                Ok((Some(output), patch))
+            })
+            .await
+    }
+
+    /// Consumes the [`ServerVec`], returning the inner [`ServerSignal`].
+    pub fn into_signal(self) -> ServerSignal<Vec<T>> {
+        self.signal
+    }
+}
+
+impl<T, K> ops::Deref for ServerVec<T, K> {
+    type Target = ServerSignal<Vec<T>>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.signal
+    }
+}
+
+impl<T, K> ops::DerefMut for ServerVec<T, K> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.signal
+    }
+}
+
+/// A server signal containing a map of values, sent as a json object,
+/// which sends a single patch operation per inserted or removed entry.
+///
+/// Keys are sent as the fields of the json object, named by how serde serializes them as map keys.
+/// Updating an entry only diffs that entry.
+///
+/// The signal can be read as a `BTreeMap<K, V>`, and the inner [`ServerSignal`] is available through `Deref`,
+/// for handling messages and sending snapshots.
+/// On the client, use [`create_server_map`](crate::create_server_map) for a signal per entry.
+///
+/// # Example
+///
+/// ```ignore
+/// let mut users = ServerMap::<String, User>::new("users").unwrap();
+/// users.insert(&mut websocket, "ari".to_string(), User { online: true }).await?;
+/// users.update(&mut websocket, "ari", |user| user.online = false).await?;
+/// users.remove(&mut websocket, "ari").await?;
+/// ```
+#[derive(Clone, Debug)]
+pub struct ServerMap<K, V> {
+    signal: ServerSignal<BTreeMap<K, V>>,
+}
+
+impl<K, V> ServerMap<K, V>
+where
+    K: Ord + Serialize,
+{
+    /// Creates a new empty [`ServerMap`].
+    ///
+    /// This function can fail if serialization of an empty map fails.
+    pub fn new(name: impl Into<Cow<'static, str>>) -> Result<Self, serde_json::Error>
+    where
+        V: Serialize,
+    {
+        Ok(ServerMap::from_signal(ServerSignal::new(name)?))
+    }
+
+    /// Creates a new [`ServerMap`] from a configured [`ServerSignal`].
+    pub fn from_signal(signal: ServerSignal<BTreeMap<K, V>>) -> Self {
+        ServerMap { signal }
+    }
+
+    /// Inserts an entry, sending it as a single `add` operation.
+    ///
+    /// Returns the previous value of the entry, if there was one.
+    pub async fn insert<W>(
+        &mut self,
+        transport: &mut W,
+        key: K,
+        value: V,
+    ) -> Result<Option<V>, Error>
+    where
+        V: Serialize,
+        W: Transport,
+    {
+        self.signal
+            .with_patch(transport, |entries| {
+                let patch = Patch(vec![PatchOperation::Add(AddOperation {
+                    path: pointer(map_key(&key)?),
+                    value: serde_json::to_value(&value)?,
+                })]);
+                Ok((entries.insert(key, value), patch))
+            })
+            .await
+    }
+
+    /// Removes an entry, sending a single `remove` operation.
+    ///
+    /// Returns the removed value, or `None` if there is no entry with the key.
+    pub async fn remove<Q, W>(&mut self, transport: &mut W, key: &Q) -> Result<Option<V>, Error>
+    where
+        K: std::borrow::Borrow<Q>,
+        Q: Ord + Serialize + ?Sized,
+        V: Serialize,
+        W: Transport,
+    {
+        if !self.signal.contains_key(key) {
+            return Ok(None);
+        }
+        self.signal
+            .with_patch(transport, |entries| {
+                let patch = Patch(vec![PatchOperation::Remove(RemoveOperation {
+                    path: pointer(map_key(key)?),
+                })]);
+                Ok((entries.remove(key), patch))
+            })
+            .await
+    }
+
+    /// Modifies an entry in a closure, sending the diff of that entry only.
+    ///
+    /// Returns the output of the closure, or `None` if there is no entry with the key.
+    pub async fn update<Q, O, W>(
+        &mut self,
+        transport: &mut W,
+        key: &Q,
+        f: impl FnOnce(&mut V) -> O,
+    ) -> Result<Option<O>, Error>
+    where
+        K: std::borrow::Borrow<Q>,
+        Q: Ord + Serialize + ?Sized,
+        V: Serialize,
+        W: Transport,
+    {
+        if !self.signal.contains_key(key) {
+            return Ok(None);
+        }
+        let strategy = self.signal.strategy().clone();
+        self.signal
+            .with_patch(transport, |entries| {
+                let value = entries.get_mut(key).unwrap();
+                let old_json = serde_json::to_value(&*value)?;
+                let output = f(value);
+                let new_json = serde_json::to_value(&*value)?;
+                let patch = nest(strategy.diff(&old_json, &new_json), map_key(key)?);
+                Ok((Some(output), patch))
+            })
+            .await
+    }
+
+    /// Consumes the [`ServerMap`], returning the inner [`ServerSignal`].
+    pub fn into_signal(self) -> ServerSignal<BTreeMap<K, V>> {
+        self.signal
+    }
+}
+
+impl<K, V> ops::Deref for ServerMap<K, V> {
+    type Target = ServerSignal<BTreeMap<K, V>>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.signal
+    }
+}
+
+impl<K, V> ops::DerefMut for ServerMap<K, V> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.signal
+    }
+}
+
+/// Returns the pointer to a child of the root.
+fn pointer(token: impl fmt::Display) -> PointerBuf {
+    let mut path = PointerBuf::root();
+    path.push_back(token.to_string());
+    path
+}
+
+/// Returns the json object field a map key is serialized as.
+///
+/// This can differ from the key's `Display` output, such as for enums renamed by serde.
+fn map_key<Q>(key: &Q) -> Result<String, serde_json::Error>
+where
+    Q: Serialize + ?Sized,
+{
+    struct Entry<'a, Q: ?Sized>(&'a Q);
+
+    impl<Q> Serialize for Entry<'_, Q>
+    where
+        Q: Serialize + ?Sized,
+    {
+        fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+        where
+            S: serde::Serializer,
+        {
+            let mut map = serializer.serialize_map(Some(1))?;
+            map.serialize_entry(self.0, &())?;
+            map.end()
+        }
+    }
+
+    match serde_json::to_value(Entry(key))? {
+        Value::Object(map) => Ok(map
+            .into_iter()
+            .next()
+            .map(|(key, _)| key)
+            .unwrap_or_default()),
+        _ => unreachable!("maps serialize to json objects"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::channel::mpsc::{self, UnboundedReceiver, UnboundedSender};
+    use futures::executor::block_on;
+    use serde_json::json;
+
+    use super::*;
+    use crate::fixtures::received;
+    use crate::{Frame, ServerSignalMessage};
+
+    #[derive(Clone, Debug, PartialEq, Serialize)]
+    struct Todo {
+        id: u32,
+        title: &'static str,
+        done: bool,
+    }
+
+    fn todo(id: u32, title: &'static str) -> Todo {
+        Todo {
+            id,
+            title,
+            done: false,
+        }
+    }
+
+    /// Mirrors the json value of a signal on the client, from the messages it receives.
+    struct Client {
+        rx: UnboundedReceiver<Frame>,
+        json: Value,
+    }
+
+    impl Client {
+        /// Applies the received messages, asserting they are single updates after the first snapshot.
+        fn sync(&mut self) -> &Value {
+            let messages = received(&mut self.rx);
+            for message in &messages {
+                if !self.json.is_null() {
+                    assert!(matches!(message, ServerSignalMessage::Update(_)));
+                }
+                message.apply(&mut self.json).unwrap();
+            }
+            &self.json
+        }
+    }
+
+    fn connect() -> (UnboundedSender<Frame>, Client) {
+        let (tx, rx) = mpsc::unbounded();
+        (
+            tx,
+            Client {
+                rx,
+                json: Value::Null,
+            },
+        )
+    }
+
+    #[test]
+    fn vec_patches_rebuild_serialized_value() {
+        let (mut tx, mut client) = connect();
+        let mut todos = ServerVec::new("todos", |todo: &Todo| todo.id).unwrap();
+
+        block_on(todos.push(&mut tx, todo(1, "one"))).unwrap();
+        block_on(todos.push(&mut tx, todo(2, "two"))).unwrap();
+        block_on(todos.insert(&mut tx, 0, todo(3, "three"))).unwrap();
+        assert_eq!(
+            client.sync(),
+            &serde_json::to_value(todos.as_ref()).unwrap()
+        );
+
+        block_on(todos.update(&mut tx, &1, |todo| todo.done = true)).unwrap();
+        assert_eq!(
+            client.sync(),
+            &serde_json::to_value(todos.as_ref()).unwrap()
+        );
+        assert_eq!(
+            client.json[1],
+            json!({ "id": 1, "title": "one", "done": true })
+        );
+
+        let removed = block_on(todos.remove(&mut tx, &3)).unwrap();
+        assert_eq!(removed, Some(todo(3, "three")));
+        assert_eq!(
+            client.sync(),
+            &serde_json::to_value(todos.as_ref()).unwrap()
+        );
+        assert_eq!(client.json.as_array().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn vec_ignores_missing_keys() {
+        let (mut tx, mut client) = connect();
+        let mut todos = ServerVec::new("todos", |todo: &Todo| todo.id).unwrap();
+        block_on(todos.push(&mut tx, todo(1, "one"))).unwrap();
+        client.sync();
+
+        assert_eq!(block_on(todos.remove(&mut tx, &2)).unwrap(), None);
+        assert_eq!(block_on(todos.update(&mut tx, &2, |_| ())).unwrap(), None);
+        assert!(received(&mut client.rx).is_empty());
+    }
+
+    #[test]
+    fn map_patches_rebuild_serialized_value() {
+        let (mut tx, mut client) = connect();
+        let mut users = ServerMap::<String, Todo>::new("users").unwrap();
+
+        block_on(users.insert(&mut tx, "ari".to_string(), todo(1, "one"))).unwrap();
+        block_on(users.insert(&mut tx, "a/b~c".to_string(), todo(2, "two"))).unwrap();
+        assert_eq!(
+            client.sync(),
+            &serde_json::to_value(users.as_ref()).unwrap()
+        );
+
+        let previous = block_on(users.insert(&mut tx, "ari".to_string(), todo(3, "three")));
+        assert_eq!(previous.unwrap(), Some(todo(1, "one")));
+        block_on(users.update(&mut tx, "a/b~c", |todo| todo.done = true)).unwrap();
+        assert_eq!(
+            client.sync(),
+            &serde_json::to_value(users.as_ref()).unwrap()
+        );
+
+        block_on(users.remove(&mut tx, "ari")).unwrap();
+        assert_eq!(
+            client.sync(),
+            &serde_json::to_value(users.as_ref()).unwrap()
+        );
+        assert_eq!(
+            client.json,
+            json!({ "a/b~c": { "id": 2, "title": "two", "done": true } })
+        );
+    }
+
+    #[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+    #[serde(rename_all = "snake_case")]
+    enum Room {
+        Lobby,
+        GameRoom,
+    }
+
+    impl fmt::Display for Room {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            match self {
+                Room::Lobby => f.write_str("Lobby"),
+                Room::GameRoom => f.write_str("Game Room"),
+            }
+        }
+    }
+
+    #[test]
+    fn map_keys_use_serialized_names() {
+        let (mut tx, mut client) = connect();
+        let mut rooms = ServerMap::<Room, u32>::new("rooms").unwrap();
+
+        block_on(rooms.insert(&mut tx, Room::Lobby, 1)).unwrap();
+        block_on(rooms.insert(&mut tx, Room::GameRoom, 2)).unwrap();
+        block_on(rooms.update(&mut tx, &Room::GameRoom, |count| *count += 1)).unwrap();
+        assert_eq!(client.sync(), &json!({ "lobby": 1, "game_room": 3 }));
+
+        block_on(rooms.remove(&mut tx, &Room::Lobby)).unwrap();
+        assert_eq!(
+            client.sync(),
+            &serde_json::to_value(rooms.as_ref()).unwrap()
+        );
+        assert_eq!(client.json, json!({ "game_room": 3 }));
+    }
+
+    #[test]
+    fn map_keys_of_integers_are_strings() {
+        assert_eq!(map_key(&42u32).unwrap(), "42");
+        assert_eq!(map_key("ari").unwrap(), "ari");
+    }
+}
@@ -0,0 +1,175 @@
+use std::io::{Read, Write};
+
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+
+use crate::{CodecError, Frame};
+
+/// The magic bytes at the start of every gzip stream, used to detect compressed frames.
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+
+/// Compresses frames above a size threshold with gzip.
+///
+/// Compressed frames are sent as binary frames, and are detected and decompressed by the client
+/// regardless of the codec in use. The `compression` feature must be enabled on the client too.
+///
+/// # Example
+///
+/// ```
+/// # use leptos_server_signal::Compression;
+/// let compression = Compression::new().threshold(16 * 1024).level(9);
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Compression {
+    threshold: usize,
+    level: u32,
+}
+
+impl Compression {
+    /// Creates a new [`Compression`], compressing frames of at least 1 KiB with the default level.
+    pub fn new() -> Self {
+        Compression {
+            threshold: 1024,
+            level: 6,
+        }
+    }
+
+    /// Sets the minimum size in bytes of frames which are compressed.
+    pub fn threshold(mut self, threshold: usize) -> Self {
+        self.threshold = threshold;
+        self
+    }
+
+    /// Sets the compression level, between `0` (fastest) and `9` (smallest).
+    pub fn level(mut self, level: u32) -> Self {
+        self.level = level.min(9);
+        self
+    }
+
+    /// Compresses a frame if it is at least the threshold size.
+    ///
+    /// The frame is returned unchanged if compressing it would not make it smaller.
+    pub fn compress(&self, frame: Frame) -> Result<Frame, CodecError> {
+        if frame.len() < self.threshold {
+            return Ok(frame);
+        }
+        let bytes = match &frame {
+            Frame::Text(text) => text.as_bytes(),
+            Frame::Binary(bytes) => bytes.as_slice(),
+        };
+        let mut encoder = GzEncoder::new(
+            Vec::with_capacity(bytes.len() / 4),
+            flate2::Compression::new(self.level),
+        );
+        encoder.write_all(bytes).map_err(CodecError::new)?;
+        let compressed = encoder.finish().map_err(CodecError::new)?;
+        if compressed.len() >= bytes.len() {
+            return Ok(frame);
+        }
+        Ok(Frame::Binary(compressed))
+    }
+}
+
+impl Default for Compression {
+    fn default() -> Self {
+        Compression::new()
+    }
+}
+
+/// Decompresses a frame produced by [`Compression::compress`].
+///
+/// Frames which are not gzip compressed are returned unchanged.
+pub fn decompress(frame: Frame) -> Result<Frame, CodecError> {
+    match frame {
+        Frame::Binary(bytes) if bytes.starts_with(&GZIP_MAGIC) => {
+            let mut decompressed = Vec::with_capacity(bytes.len() * 4);
+            GzDecoder::new(bytes.as_slice())
+                .read_to_end(&mut decompressed)
+                .map_err(CodecError::new)?;
+            Ok(Frame::Binary(decompressed))
+        }
+        frame => Ok(frame),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::{Codec, JsonCodec, ServerSignalMessage, ServerSignalSnapshot};
+
+    fn large_message() -> ServerSignalMessage {
+        let items: Vec<_> = (0..200)
+            .map(|id| json!({ "id": id, "done": false }))
+            .collect();
+        ServerSignalSnapshot::new_from_json("todos", json!(items)).into()
+    }
+
+    /// Returns bytes which gzip cannot make smaller.
+    fn incompressible(len: usize) -> Vec<u8> {
+        let mut state: u32 = 0x1234_5678;
+        (0..len)
+            .map(|_| {
+                state = state.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+                (state >> 24) as u8
+            })
+            .collect()
+    }
+
+    #[test]
+    fn frames_below_threshold_are_unchanged() {
+        let frame = Frame::Text("{}".repeat(100));
+        let compression = Compression::new().threshold(201);
+        assert_eq!(compression.compress(frame.clone()).unwrap(), frame);
+    }
+
+    #[test]
+    fn frames_at_threshold_are_compressed() {
+        let frame = Frame::Text("{}".repeat(100));
+        let compressed = Compression::new().threshold(200).compress(frame).unwrap();
+        assert!(matches!(&compressed, Frame::Binary(bytes) if bytes.starts_with(&GZIP_MAGIC)));
+    }
+
+    #[test]
+    fn compressed_frames_round_trip() {
+        let message = large_message();
+        let frame = JsonCodec.encode_server(&message).unwrap();
+        let Frame::Text(text) = &frame else {
+            panic!("expected a text frame");
+        };
+
+        let compressed = Compression::new().compress(frame.clone()).unwrap();
+        assert!(compressed.len() < frame.len());
+
+        let decompressed = decompress(compressed).unwrap();
+        assert_eq!(decompressed, Frame::Binary(text.clone().into_bytes()));
+        assert_eq!(JsonCodec.decode_server(&decompressed).unwrap(), message);
+    }
+
+    #[test]
+    fn incompressible_frames_are_unchanged() {
+        let frame = Frame::Binary(incompressible(4096));
+        assert_eq!(Compression::new().compress(frame.clone()).unwrap(), frame);
+    }
+
+    #[test]
+    fn uncompressed_frames_are_not_decompressed() {
+        let text = Frame::Text("{}".to_string());
+        assert_eq!(decompress(text.clone()).unwrap(), text);
+        let binary = Frame::Binary(vec![0x1f, 0x00, 0x8b]);
+        assert_eq!(decompress(binary.clone()).unwrap(), binary);
+    }
+
+    #[test]
+    fn corrupted_frames_fail_to_decompress() {
+        let mut bytes = GZIP_MAGIC.to_vec();
+        bytes.extend_from_slice(&[0xff; 16]);
+        assert!(decompress(Frame::Binary(bytes)).is_err());
+    }
+
+    #[test]
+    fn level_is_clamped() {
+        assert_eq!(Compression::new().level(20), Compression::new().level(9));
+    }
+}
@@ -0,0 +1,370 @@
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+#[cfg(any(target_arch = "wasm32", test))]
+use std::collections::HashMap;
+
+#[cfg(any(target_arch = "wasm32", test))]
+use crate::ServerSignalMessage;
+
+/// Limits on the messages queued for server signals which have not been read yet.
+///
+/// Messages received for a signal before it is first read on the client are queued,
+/// and applied when it is read. Signals which are never read, such as those with a mismatched name,
+/// would otherwise queue messages forever.
+///
+/// Queued updates are collapsed into the queued snapshot where possible, so a signal usually
+/// queues a single message. When a limit is exceeded or messages expire, the signal's queue is discarded,
+/// and a resync is requested if it is read later.
+///
+/// # Example
+///
+/// ```
+/// # use std::time::Duration;
+/// # use leptos_server_signal::DelayedUpdateLimits;
+/// let limits = DelayedUpdateLimits::new()
+///     .max_per_signal(16)
+///     .max_total(256)
+///     .max_age(Duration::from_secs(10));
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DelayedUpdateLimits {
+    max_per_signal: usize,
+    max_total: usize,
+    max_age: Duration,
+}
+
+impl DelayedUpdateLimits {
+    /// Creates new [`DelayedUpdateLimits`].
+    ///
+    /// The defaults are 64 messages per signal, 1024 messages in total, and a maximum age of 30 seconds.
+    pub fn new() -> Self {
+        DelayedUpdateLimits {
+            max_per_signal: 64,
+            max_total: 1024,
+            max_age: Duration::from_secs(30),
+        }
+    }
+
+    /// Sets the number of messages queued for a signal, after which its queue is discarded.
+    pub fn max_per_signal(mut self, max: usize) -> Self {
+        self.max_per_signal = max.max(1);
+        self
+    }
+
+    /// Sets the number of messages queued for all signals, after which the oldest queues are discarded.
+    pub fn max_total(mut self, max: usize) -> Self {
+        self.max_total = max.max(1);
+        self
+    }
+
+    /// Sets how long a signal's queue is kept after its first message, before it is discarded.
+    pub fn max_age(mut self, max_age: Duration) -> Self {
+        self.max_age = max_age;
+        self
+    }
+}
+
+impl Default for DelayedUpdateLimits {
+    fn default() -> Self {
+        DelayedUpdateLimits::new()
+    }
+}
+
+/// Counters for the messages queued for server signals which have not been read yet.
+///
+/// Signals which keep being discarded are usually read with a different name on the client
+/// than the server sends them with.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct DelayedUpdateStats {
+    /// The number of messages currently queued.
+    pub queued: usize,
+    /// The number of updates collapsed into a queued snapshot.
+    pub collapsed: u64,
+    /// The number of messages discarded because a limit was exceeded.
+    pub dropped: u64,
+    /// The number of messages discarded because they expired.
+    pub expired: u64,
+    /// The number of messages discarded for each signal.
+    pub discarded: BTreeMap<Cow<'static, str>, u64>,
+}
+
+/// The queue of messages for server signals which have not been read yet.
+#[cfg(any(target_arch = "wasm32", test))]
+#[derive(Debug, Default)]
+pub(crate) struct DelayedUpdates {
+    limits: DelayedUpdateLimits,
+    queues: HashMap<Cow<'static, str>, Queue>,
+    stats: DelayedUpdateStats,
+}
+
+#[cfg(any(target_arch = "wasm32", test))]
+#[derive(Debug)]
+struct Queue {
+    // When the first message was queued, in milliseconds
+    since: f64,
+    messages: Vec<ServerSignalMessage>,
+}
+
+#[cfg(any(target_arch = "wasm32", test))]
+impl DelayedUpdates {
+    pub(crate) fn set_limits(&mut self, limits: DelayedUpdateLimits) {
+        self.limits = limits;
+    }
+
+    pub(crate) fn stats(&self) -> &DelayedUpdateStats {
+        &self.stats
+    }
+
+    /// Queues a message, returning the names of the signals whose queues were discarded.
+    ///
+    /// A snapshot replaces the signal's queue, and an update is applied to a queued snapshot if there is one.
+    pub(crate) fn push(
+        &mut self,
+        name: Cow<'static, str>,
+        message: ServerSignalMessage,
+        now: f64,
+    ) -> Vec<Cow<'static, str>> {
+        let mut discarded = self.expire(now);
+
+        let queue = self.queues.entry(name.clone()).or_insert_with(|| Queue {
+            since: now,
+            messages: Vec::new(),
+        });
+        match message {
+            ServerSignalMessage::Snapshot(snapshot) => {
+                self.stats.queued = self.stats.queued - queue.messages.len() + 1;
+                queue.messages = vec![snapshot.into()];
+            }
+            ServerSignalMessage::Update(update) => {
+                // Patches are applied atomically, leaving the snapshot unchanged on failure
+                let collapsed = match queue.messages.as_mut_slice() {
+                    [ServerSignalMessage::Snapshot(snapshot)] => {
+                        let applied = json_patch::patch(&mut snapshot.value, &update.patch).is_ok();
+                        if applied {
+                            snapshot.version = update.version;
+                        }
+                        applied
+                    }
+                    _ => false,
+                };
+                if collapsed {
+                    self.stats.collapsed += 1;
+                } else {
+                    queue.messages.push(update.into());
+                    self.stats.queued += 1;
+                }
+            }
+            // Batches are split into their messages before being queued
+            ServerSignalMessage::Batch(_) => {}
+        }
+
+        if queue.messages.len() > self.limits.max_per_signal {
+            self.discard(&name, false);
+            discarded.push(name);
+        }
+        while self.stats.queued > self.limits.max_total {
+            let Some(oldest) = self.oldest() else {
+                break;
+            };
+            self.discard(&oldest, false);
+            discarded.push(oldest);
+        }
+        discarded
+    }
+
+    /// Removes the messages queued for a signal which is being read.
+    pub(crate) fn take(&mut self, name: &str) -> Option<Vec<ServerSignalMessage>> {
+        let queue = self.queues.remove(name)?;
+        self.stats.queued -= queue.messages.len();
+        Some(queue.messages)
+    }
+
+    /// Discards the queues older than the maximum age, returning their names.
+    pub(crate) fn expire(&mut self, now: f64) -> Vec<Cow<'static, str>> {
+        let max_age = self.limits.max_age.as_secs_f64() * 1000.0;
+        let expired: Vec<_> = self
+            .queues
+            .iter()
+            .filter(|(_, queue)| now - queue.since > max_age)
+            .map(|(name, _)| name.clone())
+            .collect();
+        for name in &expired {
+            self.discard(name, true);
+        }
+        expired
+    }
+
+    fn discard(&mut self, name: &str, expired: bool) {
+        let Some((name, queue)) = self.queues.remove_entry(name) else {
+            return;
+        };
+        let count = queue.messages.len() as u64;
+        self.stats.queued -= queue.messages.len();
+        if expired {
+            self.stats.expired += count;
+        } else {
+            self.stats.dropped += count;
+        }
+        *self.stats.discarded.entry(name).or_default() += count;
+    }
+
+    /// Returns the name of the signal whose queue is the oldest.
+    fn oldest(&self) -> Option<Cow<'static, str>> {
+        self.queues
+            .iter()
+            .min_by(|(_, a), (_, b)| a.since.total_cmp(&b.since))
+            .map(|(name, _)| name.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use json_patch::Patch;
+    use serde_json::{json, Value};
+
+    use super::*;
+    use crate::{ServerSignalSnapshot, ServerSignalUpdate};
+
+    fn snapshot(name: &'static str, version: u64, value: Value) -> ServerSignalMessage {
+        ServerSignalSnapshot::new_from_json(name, value)
+            .with_version(version)
+            .into()
+    }
+
+    fn update(name: &'static str, version: u64, patch: Value) -> ServerSignalMessage {
+        let patch: Patch = serde_json::from_value(patch).unwrap();
+        ServerSignalUpdate::new_from_patch(name, patch)
+            .with_version(version)
+            .into()
+    }
+
+    fn replace_value(name: &'static str, version: u64, value: i32) -> ServerSignalMessage {
+        update(
+            name,
+            version,
+            json!([{ "op": "replace", "path": "/value", "value": value }]),
+        )
+    }
+
+    #[test]
+    fn updates_are_queued_in_order() {
+        let mut delayed = DelayedUpdates::default();
+        assert!(delayed
+            .push("a".into(), replace_value("a", 1, 1), 0.0)
+            .is_empty());
+        assert!(delayed
+            .push("a".into(), replace_value("a", 2, 2), 0.0)
+            .is_empty());
+        assert_eq!(delayed.stats().queued, 2);
+
+        let versions: Vec<_> = delayed
+            .take("a")
+            .unwrap()
+            .iter()
+            .map(|message| message.version())
+            .collect();
+        assert_eq!(versions, [Some(1), Some(2)]);
+        assert_eq!(delayed.stats().queued, 0);
+        assert!(delayed.take("a").is_none());
+    }
+
+    #[test]
+    fn snapshot_replaces_queue() {
+        let mut delayed = DelayedUpdates::default();
+        delayed.push("a".into(), replace_value("a", 1, 1), 0.0);
+        delayed.push("a".into(), replace_value("a", 2, 2), 0.0);
+        delayed.push("a".into(), snapshot("a", 5, json!({ "value": 5 })), 0.0);
+        assert_eq!(delayed.stats().queued, 1);
+        assert_eq!(
+            delayed.take("a").unwrap(),
+            [snapshot("a", 5, json!({ "value": 5 }))]
+        );
+    }
+
+    #[test]
+    fn updates_collapse_into_snapshot() {
+        let mut delayed = DelayedUpdates::default();
+        delayed.push("a".into(), snapshot("a", 1, json!({ "value": 0 })), 0.0);
+        delayed.push("a".into(), replace_value("a", 2, 1), 0.0);
+        delayed.push("a".into(), replace_value("a", 3, 2), 0.0);
+        assert_eq!(delayed.stats().queued, 1);
+        assert_eq!(delayed.stats().collapsed, 2);
+        assert_eq!(
+            delayed.take("a").unwrap(),
+            [snapshot("a", 3, json!({ "value": 2 }))]
+        );
+    }
+
+    #[test]
+    fn update_which_cannot_be_collapsed_is_queued() {
+        let mut delayed = DelayedUpdates::default();
+        delayed.push("a".into(), snapshot("a", 1, json!({})), 0.0);
+        delayed.push("a".into(), replace_value("a", 2, 1), 0.0);
+        assert_eq!(delayed.stats().queued, 2);
+        assert_eq!(delayed.stats().collapsed, 0);
+        assert_eq!(
+            delayed.take("a").unwrap(),
+            [snapshot("a", 1, json!({})), replace_value("a", 2, 1)]
+        );
+    }
+
+    #[test]
+    fn queue_exceeding_signal_limit_is_discarded() {
+        let mut delayed = DelayedUpdates::default();
+        delayed.set_limits(DelayedUpdateLimits::new().max_per_signal(2));
+        delayed.push("a".into(), replace_value("a", 1, 1), 0.0);
+        delayed.push("a".into(), replace_value("a", 2, 2), 0.0);
+        delayed.push("b".into(), replace_value("b", 1, 1), 0.0);
+
+        let discarded = delayed.push("a".into(), replace_value("a", 3, 3), 0.0);
+        assert_eq!(discarded, ["a"]);
+        assert!(delayed.take("a").is_none());
+        assert_eq!(delayed.stats().queued, 1);
+        assert_eq!(delayed.stats().dropped, 3);
+        assert_eq!(delayed.stats().discarded.get("a"), Some(&3));
+        assert!(delayed.take("b").is_some());
+    }
+
+    #[test]
+    fn oldest_queues_are_discarded_over_total_limit() {
+        let mut delayed = DelayedUpdates::default();
+        delayed.set_limits(DelayedUpdateLimits::new().max_total(2));
+        delayed.push("a".into(), replace_value("a", 1, 1), 0.0);
+        delayed.push("b".into(), replace_value("b", 1, 1), 1.0);
+
+        let discarded = delayed.push("c".into(), replace_value("c", 1, 1), 2.0);
+        assert_eq!(discarded, ["a"]);
+        assert_eq!(delayed.stats().queued, 2);
+        assert_eq!(delayed.stats().dropped, 1);
+        assert!(delayed.take("b").is_some());
+        assert!(delayed.take("c").is_some());
+    }
+
+    #[test]
+    fn queues_expire_after_max_age() {
+        let mut delayed = DelayedUpdates::default();
+        delayed.set_limits(DelayedUpdateLimits::new().max_age(Duration::from_secs(1)));
+        delayed.push("a".into(), replace_value("a", 1, 1), 0.0);
+        delayed.push("a".into(), replace_value("a", 2, 2), 900.0);
+        assert!(delayed.expire(1000.0).is_empty());
+
+        // The age is measured from the first queued message
+        assert_eq!(delayed.expire(1001.0), ["a"]);
+        assert_eq!(delayed.stats().queued, 0);
+        assert_eq!(delayed.stats().expired, 2);
+        assert_eq!(delayed.stats().discarded.get("a"), Some(&2));
+    }
+
+    #[test]
+    fn push_expires_other_queues() {
+        let mut delayed = DelayedUpdates::default();
+        delayed.set_limits(DelayedUpdateLimits::new().max_age(Duration::from_secs(1)));
+        delayed.push("a".into(), replace_value("a", 1, 1), 0.0);
+
+        let discarded = delayed.push("b".into(), replace_value("b", 1, 1), 2000.0);
+        assert_eq!(discarded, ["a"]);
+        assert!(delayed.take("b").is_some());
+    }
+}
@@ -0,0 +1,60 @@
+use json_patch::Patch;
+
+/// Computes json patch operations between two values without serializing them in full.
+///
+/// This is usually derived with `#[derive(Diff)]` when the `derive` feature is enabled,
+/// which compares each field and emits an operation for changed fields only.
+/// Server signals use it when created with [`ServerSignal::diffed`](crate::ServerSignal::diffed),
+/// avoiding serializing and diffing the whole value on every change.
+///
+/// # Example
+///
+/// ```ignore
+/// #[derive(Clone, Default, PartialEq, Serialize, Deserialize, Diff)]
+/// pub struct State {
+///     pub count: i32,
+///     pub messages: Vec<String>,
+///     #[diff(nested)]
+///     pub settings: Settings,
+/// }
+/// ```
+pub trait Diff {
+    /// Appends the operations turning `old` into `self` to `patch`.
+    ///
+    /// `path` is the json pointer of this value within the signal, and is empty at the root.
+    fn diff(&self, old: &Self, path: &str, patch: &mut Patch) -> Result<(), serde_json::Error>;
+}
+
+/// Items used by code generated by the derive macros.
+#[doc(hidden)]
+pub mod __private {
+    use json_patch::{AddOperation, PatchOperation};
+    use serde::Serialize;
+
+    pub use json_patch::Patch;
+    pub use serde_json;
+
+    /// Returns the json pointer of a field within the value at `path`.
+    pub fn join(path: &str, field: &str) -> String {
+        format!("{path}/{}", field.replace('~', "~0").replace('/', "~1"))
+    }
+
+    /// Appends an operation setting a field within the value at `path`.
+    pub fn add<V>(
+        patch: &mut Patch,
+        path: &str,
+        field: &str,
+        value: &V,
+    ) -> Result<(), serde_json::Error>
+    where
+        V: Serialize,
+    {
+        patch.0.push(PatchOperation::Add(AddOperation {
+            path: join(path, field)
+                .parse()
+                .expect("escaped json pointer is valid"),
+            value: serde_json::to_value(value)?,
+        }));
+        Ok(())
+    }
+}
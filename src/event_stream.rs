@@ -0,0 +1,199 @@
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use futures::channel::mpsc::{self, UnboundedReceiver, UnboundedSender};
+use futures::stream::Stream;
+
+use crate::Frame;
+#[cfg(feature = "msgpack")]
+use crate::{Codec, JsonCodec, MessagePackCodec};
+
+/// A stream of server-sent events, used as an alternative to websockets.
+///
+/// Each frame is sent as a single event. Events can only carry text, so binary frames produced by
+/// compression or the `MessagePackCodec` are decoded and sent as json instead.
+/// Binary frames which cannot be decoded end the stream.
+///
+/// Events only flow from the server to the client, so updates from [writable](crate::ServerSignal::writable)
+/// signals are not received. Clients resync by reopening the connection, and every new connection
+/// should begin with a snapshot of each signal.
+///
+/// The stream can be returned as a response from axum, actix and poem handlers when their feature is enabled.
+///
+/// # Example
+///
+/// ```ignore
+/// pub async fn events() -> impl IntoResponse {
+///     let (mut sender, events) = EventStream::channel();
+///     tokio::spawn(async move {
+///         let mut count = ServerSignal::<Count>::new("counter").unwrap();
+///         loop {
+///             tokio::time::sleep(Duration::from_millis(10)).await;
+///             if count.with(&mut sender, |count| count.value += 1).await.is_err() {
+///                 break;
+///             }
+///         }
+///     });
+///     events
+/// }
+/// ```
+#[derive(Debug)]
+pub struct EventStream<S> {
+    frames: S,
+}
+
+impl<S> EventStream<S>
+where
+    S: Stream<Item = Frame>,
+{
+    /// Creates a new [`EventStream`] from a stream of frames.
+    pub fn new(frames: S) -> Self {
+        EventStream { frames }
+    }
+}
+
+impl EventStream<UnboundedReceiver<Frame>> {
+    /// Creates a new [`EventStream`] along with an unbounded sender which frames can be sent through.
+    ///
+    /// The sender is a [`Transport`](crate::Transport), and can be connected to a [`ServerSignalHub`](crate::ServerSignalHub).
+    pub fn channel() -> (UnboundedSender<Frame>, Self) {
+        let (sender, receiver) = mpsc::unbounded();
+        (sender, EventStream::new(receiver))
+    }
+}
+
+impl<S> Stream for EventStream<S>
+where
+    S: Stream<Item = Frame> + Unpin,
+{
+    type Item = String;
+
+    /// Polls the next frame, encoded as a server-sent event.
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        Pin::new(&mut self.frames)
+            .poll_next(cx)
+            .map(|frame| frame.and_then(json_text).map(|text| encode_event(&text)))
+    }
+}
+
+/// Returns the json text of a frame, decoding binary frames into json.
+fn json_text(frame: Frame) -> Option<String> {
+    #[cfg(feature = "compression")]
+    let frame = crate::decompress(frame).ok()?;
+    let bytes = match frame {
+        Frame::Text(text) => return Some(text),
+        Frame::Binary(bytes) => bytes,
+    };
+    // MessagePack maps never start with a valid utf-8 byte, while json frames are only binary when compressed
+    let bytes = match String::from_utf8(bytes) {
+        Ok(text) => return Some(text),
+        Err(err) => err.into_bytes(),
+    };
+    #[cfg(feature = "msgpack")]
+    {
+        let message = MessagePackCodec.decode_server(&Frame::Binary(bytes)).ok()?;
+        match JsonCodec.encode_server(&message).ok()? {
+            Frame::Text(text) => Some(text),
+            Frame::Binary(_) => None,
+        }
+    }
+    #[cfg(not(feature = "msgpack"))]
+    {
+        let _ = bytes;
+        None
+    }
+}
+
+/// Encodes text as the data of a server-sent event, with one `data` field per line.
+fn encode_event(text: &str) -> String {
+    let mut event = String::with_capacity(text.len() + 8);
+    for line in text.split('\n') {
+        event.push_str("data: ");
+        event.push_str(line.strip_suffix('\r').unwrap_or(line));
+        event.push('\n');
+    }
+    event.push('\n');
+    event
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::executor::block_on_stream;
+    use futures::stream;
+
+    use super::*;
+    use crate::{Codec, JsonCodec, ServerSignalMessage, ServerSignalSnapshot};
+
+    fn message() -> ServerSignalMessage {
+        let values: Vec<_> = (0..100).collect();
+        ServerSignalSnapshot::new_from_json("count", serde_json::json!({ "values": values }))
+            .with_version(2)
+            .into()
+    }
+
+    fn events(frames: Vec<Frame>) -> Vec<String> {
+        block_on_stream(EventStream::new(stream::iter(frames))).collect()
+    }
+
+    fn decode_event(event: &str) -> ServerSignalMessage {
+        let text: Vec<_> = event
+            .lines()
+            .filter_map(|line| line.strip_prefix("data: "))
+            .collect();
+        JsonCodec
+            .decode_server(&Frame::Text(text.join("\n")))
+            .unwrap()
+    }
+
+    #[test]
+    fn text_frames_are_sent_as_events() {
+        assert_eq!(
+            events(vec![Frame::Text("a\nb\r\nc".to_string())]),
+            ["data: a\ndata: b\ndata: c\n\n"]
+        );
+    }
+
+    #[test]
+    fn json_frames_are_sent_unchanged() {
+        let frame = JsonCodec.encode_server(&message()).unwrap();
+        let events = events(vec![frame]);
+        assert_eq!(decode_event(&events[0]), message());
+    }
+
+    #[cfg(feature = "compression")]
+    #[test]
+    fn compressed_frames_are_sent_as_json() {
+        let frame = JsonCodec.encode_server(&message()).unwrap();
+        let frame = crate::Compression::new()
+            .threshold(0)
+            .compress(frame)
+            .unwrap();
+        assert!(matches!(frame, Frame::Binary(_)));
+
+        let events = events(vec![frame]);
+        assert_eq!(decode_event(&events[0]), message());
+    }
+
+    #[cfg(feature = "msgpack")]
+    #[test]
+    fn msgpack_frames_are_sent_as_json() {
+        let frame = MessagePackCodec.encode_server(&message()).unwrap();
+        #[cfg(feature = "compression")]
+        let frame = crate::Compression::new()
+            .threshold(0)
+            .compress(frame)
+            .unwrap();
+
+        let events = events(vec![frame]);
+        assert_eq!(decode_event(&events[0]), message());
+    }
+
+    #[test]
+    fn undecodable_frames_end_the_stream() {
+        let frames = vec![
+            Frame::Binary(vec![0x80, 0xff]),
+            Frame::Text("never sent".to_string()),
+        ];
+        assert!(events(frames).is_empty());
+    }
+}
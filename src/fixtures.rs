@@ -0,0 +1,37 @@
+use futures::channel::mpsc::UnboundedReceiver;
+use serde::{Deserialize, Serialize};
+
+use crate::{Codec, Diff, Frame, JsonCodec, ServerSignalMessage, ServerSignalUpdate};
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub(crate) struct Count {
+    pub(crate) value: i32,
+}
+
+impl Diff for Count {
+    fn diff(
+        &self,
+        old: &Self,
+        path: &str,
+        patch: &mut json_patch::Patch,
+    ) -> Result<(), serde_json::Error> {
+        if self.value != old.value {
+            crate::__private::add(patch, path, "value", &self.value)?;
+        }
+        Ok(())
+    }
+}
+
+/// Decodes the json frames received by a connection.
+pub(crate) fn received(rx: &mut UnboundedReceiver<Frame>) -> Vec<ServerSignalMessage> {
+    std::iter::from_fn(|| rx.try_recv().ok())
+        .map(|frame| JsonCodec.decode_server(&frame).unwrap())
+        .collect()
+}
+
+/// Returns an update written by a client against `version`, changing the count from `from` to `to`.
+pub(crate) fn client_update(version: u64, from: i32, to: i32) -> ServerSignalUpdate {
+    ServerSignalUpdate::new("count", &Count { value: from }, &Count { value: to })
+        .unwrap()
+        .with_version(version)
+}
@@ -0,0 +1,805 @@
+use std::borrow::Cow;
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use std::time::Duration;
+
+use futures::channel::mpsc::UnboundedSender;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::long_poll::PollSignal;
+use crate::state::SignalState;
+#[cfg(feature = "compression")]
+use crate::Compression;
+use crate::{
+    ClientSignalMessage, Codec, CodecError, Diff, DiffStrategy, Frame, JsonCodec, LongPollRequest,
+    ServerSignalMessage, ServerSignalSnapshot, ServerSignalUpdate, SignalKey, Throttle, Validator,
+};
+
+/// The number of recent updates kept to answer long-polling requests.
+const HISTORY_LEN: usize = 64;
+
+/// Identifies a connection registered with a [`ServerSignalHub`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ConnectionId(u64);
+
+/// A server signal shared between many connections.
+///
+/// The hub owns a single value of `T`. Each mutation computes one [`ServerSignalUpdate`],
+/// encodes it once per [`Codec`] in use, and sends the resulting frame to every registered connection.
+///
+/// Connections are registered with an unbounded channel sender, and the websocket handler
+/// is responsible for forwarding the received frames to the socket.
+/// A single channel can be registered with multiple hubs to share one socket between signals.
+///
+/// Cloning a hub is cheap, and all clones share the same value and connections.
+///
+/// # Example
+///
+/// ```ignore
+/// let count = ServerSignalHub::<Count>::new("counter").unwrap();
+///
+/// // In the websocket handler
+/// let (tx, mut rx) = futures::channel::mpsc::unbounded();
+/// let id = count.connect(tx).unwrap();
+/// while let Some(frame) = rx.next().await {
+///     if socket.send(frame.into()).await.is_err() {
+///         break;
+///     }
+/// }
+/// count.disconnect(id);
+///
+/// // Elsewhere, updating every connected client at once
+/// count.with(|count| count.value += 1).unwrap();
+/// ```
+pub struct ServerSignalHub<T> {
+    inner: Arc<Mutex<HubState<T>>>,
+}
+
+struct HubState<T> {
+    signal: SignalState<T>,
+    // Connections start unsubscribed, until the client subscribes
+    lazy: bool,
+    connections: HashMap<ConnectionId, Connection>,
+    next_id: u64,
+    // Recent updates, oldest first, sent to long-polling clients which are behind
+    history: VecDeque<ServerSignalUpdate>,
+    // Long-polling requests waiting for the next update
+    waiters: Vec<UnboundedSender<()>>,
+}
+
+struct Connection {
+    sender: UnboundedSender<Frame>,
+    codec: Arc<dyn Codec>,
+    // Set when the client subscribes, and cleared when it unsubscribes
+    subscribed: bool,
+}
+
+impl<T> ServerSignalHub<T> {
+    /// Creates a new [`ServerSignalHub`], initializing `T` to default.
+    ///
+    /// This function can fail if serialization of `T` fails.
+    pub fn new(name: impl Into<Cow<'static, str>>) -> Result<Self, serde_json::Error>
+    where
+        T: Default + Serialize,
+    {
+        ServerSignalHub::new_with_value(name, T::default())
+    }
+
+    /// Creates a new [`ServerSignalHub`] with an initial value, for types which do not implement `Default`.
+    ///
+    /// This function can fail if serialization of `T` fails.
+    pub fn new_with_value(
+        name: impl Into<Cow<'static, str>>,
+        value: T,
+    ) -> Result<Self, serde_json::Error>
+    where
+        T: Serialize,
+    {
+        let mut signal = SignalState::new(name.into(), value)?;
+        // Connections are sent a snapshot when registered, so changes are always sent as diffs
+        signal.synced = true;
+        Ok(ServerSignalHub {
+            inner: Arc::new(Mutex::new(HubState {
+                signal,
+                lazy: false,
+                connections: HashMap::new(),
+                next_id: 0,
+                history: VecDeque::new(),
+                waiters: Vec::new(),
+            })),
+        })
+    }
+
+    /// Creates a new [`ServerSignalHub`] named by a [`SignalKey`], initializing `T` to default.
+    ///
+    /// The key ensures the signal's name and type match the client's.
+    pub fn from_key<K>(_key: K) -> Result<Self, serde_json::Error>
+    where
+        K: SignalKey<Value = T>,
+        T: Default + Serialize,
+    {
+        ServerSignalHub::new(K::NAME)
+    }
+
+    /// Allows clients to write to the signal, accepting updates for which `validate` returns `true`.
+    ///
+    /// Without this, updates written by clients are rejected.
+    pub fn writable(self, validate: impl Fn(&T) -> bool + Send + Sync + 'static) -> Self {
+        self.lock().signal.validator = Some(Validator::new(validate));
+        self
+    }
+
+    /// Limits how often changes are sent to at most once per `interval`.
+    ///
+    /// Mutations are applied immediately, while changes made within `interval` of the last send
+    /// are held back and coalesced into a single update.
+    /// Pending changes are sent by the next mutation after the interval has passed,
+    /// or by [`ServerSignalHub::flush`].
+    ///
+    /// Nothing sends pending changes on its own, so [`ServerSignalHub::flush`] must be called on a timer
+    /// ticking every `interval`. Otherwise the last change before a quiet period is held back
+    /// until the next mutation.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// let count = ServerSignalHub::<Count>::new("counter")
+    ///     .unwrap()
+    ///     .throttle(Duration::from_millis(100));
+    ///
+    /// let hub = count.clone();
+    /// tokio::spawn(async move {
+    ///     let mut interval = tokio::time::interval(Duration::from_millis(100));
+    ///     loop {
+    ///         interval.tick().await;
+    ///         hub.flush().unwrap();
+    ///     }
+    /// });
+    /// ```
+    pub fn throttle(self, interval: Duration) -> Self {
+        self.lock().signal.throttle = Throttle::new(interval);
+        self
+    }
+
+    /// Computes diffs with the value's [`Diff`] implementation, instead of serializing and diffing the whole value.
+    ///
+    /// See [`ServerSignal::diffed`](crate::ServerSignal::diffed).
+    pub fn diffed(self) -> Self
+    where
+        T: Diff + Clone,
+    {
+        self.lock().signal.track();
+        self
+    }
+
+    /// Sets the strategy used to compute diffs, which defaults to [`JsonDiff`](crate::JsonDiff).
+    ///
+    /// This is not used by hubs with [`Diff`] implementations, which diff themselves.
+    pub fn diff_strategy(self, strategy: Arc<dyn DiffStrategy>) -> Self {
+        self.lock().signal.strategy = strategy;
+        self
+    }
+
+    /// Sends a snapshot instead of a diff when the json-encoded diff is larger than
+    /// `threshold` times the size of the full value.
+    ///
+    /// This does not apply to hubs with [`Diff`] implementations.
+    pub fn snapshot_threshold(self, threshold: f64) -> Self {
+        self.lock().signal.snapshot_threshold = Some(threshold);
+        self
+    }
+
+    /// Sends nothing to a connection until its client subscribes to the signal, by reading it on the page.
+    ///
+    /// Without this, a snapshot is sent when a connection is registered, followed by every update.
+    /// Subscribe requests must be passed to [`ServerSignalHub::handle_message`].
+    pub fn lazy(self) -> Self {
+        self.lock().lazy = true;
+        self
+    }
+
+    /// Compresses frames sent to connections which exceed the threshold size.
+    #[cfg(feature = "compression")]
+    pub fn compression(self, compression: Compression) -> Self {
+        self.lock().signal.compression = Some(compression);
+        self
+    }
+
+    /// Registers a connection using the [`JsonCodec`], returning its id.
+    ///
+    /// A snapshot of the current value is sent immediately, followed by every update frame
+    /// until the connection is disconnected, or the receiving end of the channel is dropped.
+    /// If the hub is [lazy](ServerSignalHub::lazy), nothing is sent until the client subscribes.
+    ///
+    /// This function can fail if encoding of the snapshot fails.
+    pub fn connect(&self, sender: UnboundedSender<Frame>) -> Result<ConnectionId, CodecError> {
+        self.connect_with_codec(sender, Arc::new(JsonCodec))
+    }
+
+    /// Registers a connection which encodes frames with `codec`, returning its id.
+    ///
+    /// See [`ServerSignalHub::connect`].
+    pub fn connect_with_codec(
+        &self,
+        sender: UnboundedSender<Frame>,
+        codec: Arc<dyn Codec>,
+    ) -> Result<ConnectionId, CodecError> {
+        let mut state = self.lock();
+        let id = ConnectionId(state.next_id);
+        state.next_id += 1;
+        let subscribed = !state.lazy;
+        if subscribed {
+            let snapshot = state.signal.encode(codec.as_ref(), &state.snapshot())?;
+            if sender.unbounded_send(snapshot).is_err() {
+                return Ok(id);
+            }
+        }
+        state.connections.insert(
+            id,
+            Connection {
+                sender,
+                codec,
+                subscribed,
+            },
+        );
+        Ok(id)
+    }
+
+    /// Removes a connection from the hub, returning `true` if it was registered.
+    pub fn disconnect(&self, id: ConnectionId) -> bool {
+        self.lock().connections.remove(&id).is_some()
+    }
+
+    /// Handles a message received from a connection, returning `true` if it was for this signal.
+    ///
+    /// Resync requests are answered with a snapshot of the full value, sent to that connection only,
+    /// and updates written by the client are passed to [`ServerSignalHub::apply_update`].
+    /// Subscribe requests start sending updates to that connection with a snapshot, if it was not subscribed,
+    /// and unsubscribe requests stop them after the client's last reader of the signal is disposed.
+    pub fn handle_message(
+        &self,
+        id: ConnectionId,
+        message: &ClientSignalMessage,
+    ) -> Result<bool, CodecError>
+    where
+        T: Serialize + for<'de> Deserialize<'de>,
+    {
+        if message.name() != self.lock().signal.name {
+            return Ok(false);
+        }
+        match message {
+            ClientSignalMessage::Subscribe { .. } | ClientSignalMessage::Resync { .. } => {
+                let mut state = self.lock();
+                let Some(connection) = state.connections.get_mut(&id) else {
+                    return Ok(true);
+                };
+                let subscribed = std::mem::replace(&mut connection.subscribed, true);
+                // Subscribed connections already have the current value
+                if !subscribed || matches!(message, ClientSignalMessage::Resync { .. }) {
+                    let snapshot = state.snapshot();
+                    state.send_to(id, &snapshot)?;
+                }
+            }
+            ClientSignalMessage::Unsubscribe { .. } => {
+                let mut state = self.lock();
+                if let Some(connection) = state.connections.get_mut(&id) {
+                    connection.subscribed = false;
+                }
+            }
+            ClientSignalMessage::Update(update) => {
+                self.apply_update(id, update)?;
+            }
+        }
+        Ok(true)
+    }
+
+    /// Applies an update written by a connection, returning `true` if it was accepted.
+    ///
+    /// Accepted updates are sent to every connection with the next version.
+    /// Rejected updates are answered with a snapshot sent to that connection only,
+    /// reverting the change on the client.
+    /// Updates are rejected if the hub is not [writable](ServerSignalHub::writable),
+    /// the update was written against an older version than the current one,
+    /// the patch cannot be applied, or the patched value fails validation.
+    /// Updates which change nothing are answered with a snapshot sent to that connection only.
+    pub fn apply_update(
+        &self,
+        id: ConnectionId,
+        update: &ServerSignalUpdate,
+    ) -> Result<bool, CodecError>
+    where
+        T: Serialize + for<'de> Deserialize<'de>,
+    {
+        let mut state = self.lock();
+        // Pending changes are sent first, so the update is applied on top of the current value
+        state.flush()?;
+
+        let accepted = state.signal.accept(update);
+        // The client still waits for a reply to its update if nothing changed
+        if !accepted || !state.broadcast()? {
+            let snapshot = state.snapshot();
+            state.send_to(id, &snapshot)?;
+        }
+        Ok(accepted)
+    }
+
+    /// Returns the number of registered connections.
+    pub fn connection_count(&self) -> usize {
+        self.lock().connections.len()
+    }
+
+    /// Returns the number of registered connections subscribed to the signal.
+    ///
+    /// When this is zero, no updates are sent to websocket connections, so computing new values can be skipped,
+    /// unless the hub also answers long-polling requests.
+    pub fn subscriber_count(&self) -> usize {
+        let state = self.lock();
+        state
+            .connections
+            .values()
+            .filter(|connection| connection.subscribed)
+            .count()
+    }
+
+    /// Modifies the signal in a closure, and sends the json diff to every connection after modifying.
+    ///
+    /// The diff is computed once regardless of the number of connections, and encoded once per codec.
+    /// Connections whose receiver has been dropped are removed from the hub.
+    /// If the hub is [throttled](ServerSignalHub::throttle), the diff may be held back until the next flush.
+    /// If `f` panics, the changes it made before panicking are sent with the next update.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// let count = ServerSignalHub::<Count>::new("counter").unwrap();
+    /// count.with(|count| {
+    ///     count.value += 1;
+    /// })?;
+    /// ```
+    pub fn with<O>(&self, f: impl FnOnce(&mut T) -> O) -> Result<O, CodecError>
+    where
+        T: Serialize,
+    {
+        let mut state = self.lock();
+        let output = f(&mut state.signal.value);
+        if state.signal.throttle.ready() {
+            state.broadcast()?;
+        }
+        Ok(output)
+    }
+
+    /// Sends any changes held back by [throttling](ServerSignalHub::throttle) to every connection.
+    ///
+    /// Throttled hubs must call this on a timer, see [`ServerSignalHub::throttle`].
+    pub fn flush(&self) -> Result<(), CodecError>
+    where
+        T: Serialize,
+    {
+        self.lock().flush()
+    }
+
+    /// Returns a clone of the current value.
+    pub fn get(&self) -> T
+    where
+        T: Clone,
+    {
+        self.lock().signal.value.clone()
+    }
+
+    /// Reads the current value in a closure.
+    pub fn read<O>(&self, f: impl FnOnce(&T) -> O) -> O {
+        f(&self.lock().signal.value)
+    }
+
+    /// Returns a clone of the current json value.
+    pub fn json_value(&self) -> Value {
+        self.lock().signal.json_value.clone()
+    }
+
+    /// Returns a snapshot of the current value and version.
+    ///
+    /// This can be passed to [`provide_initial_snapshot`](crate::provide_initial_snapshot)
+    /// while rendering on the server, so the client starts from the current value.
+    pub fn snapshot(&self) -> ServerSignalSnapshot {
+        self.lock().signal.snapshot()
+    }
+}
+
+impl<T> ServerSignalHub<T> {
+    /// Locks the hub's state, even if a closure passed to [`ServerSignalHub::with`] panicked while holding it.
+    ///
+    /// The state stays consistent after such a panic, since changes to the value are only
+    /// recorded as sent once they have been sent, so one bad update does not take down every connection.
+    fn lock(&self) -> MutexGuard<'_, HubState<T>> {
+        self.inner.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Returns the number of long-polling requests waiting for the next update.
+    #[cfg(test)]
+    pub(crate) fn waiter_count(&self) -> usize {
+        self.lock().waiters.len()
+    }
+}
+
+impl<T> HubState<T> {
+    fn snapshot(&self) -> ServerSignalMessage {
+        self.signal.snapshot().into()
+    }
+
+    fn flush(&mut self) -> Result<(), CodecError>
+    where
+        T: Serialize,
+    {
+        if self.signal.throttle.is_pending() {
+            self.broadcast()?;
+        }
+        Ok(())
+    }
+
+    /// Sends the changes since the last sent value to every connection, returning `false` if nothing changed.
+    fn broadcast(&mut self) -> Result<bool, CodecError>
+    where
+        T: Serialize,
+    {
+        let Some((message, new_json)) = self.signal.next_message()? else {
+            self.signal.throttle.sent();
+            return Ok(false);
+        };
+        let mut frames: HashMap<&'static str, Frame> = HashMap::new();
+        for connection in self
+            .connections
+            .values()
+            .filter(|connection| connection.subscribed)
+        {
+            let protocol = connection.codec.protocol();
+            if !frames.contains_key(protocol) {
+                let frame = self.signal.encode(connection.codec.as_ref(), &message)?;
+                frames.insert(protocol, frame);
+            }
+        }
+        match &message {
+            ServerSignalMessage::Update(update) => {
+                if self.history.len() == HISTORY_LEN {
+                    self.history.pop_front();
+                }
+                self.history.push_back(update.clone());
+            }
+            // Long-polling clients which are behind can no longer catch up through updates
+            _ => self.history.clear(),
+        }
+        self.connections.retain(|_, connection| {
+            if !connection.subscribed {
+                return !connection.sender.is_closed();
+            }
+            let frame = frames[connection.codec.protocol()].clone();
+            connection.sender.unbounded_send(frame).is_ok()
+        });
+        self.signal.commit(&message, new_json)?;
+        for waiter in self.waiters.drain(..) {
+            let _ = waiter.unbounded_send(());
+        }
+        Ok(true)
+    }
+
+    /// Sends a message to a single connection, removing it if its receiver has been dropped.
+    fn send_to(
+        &mut self,
+        id: ConnectionId,
+        message: &ServerSignalMessage,
+    ) -> Result<(), CodecError> {
+        if let Some(connection) = self.connections.get(&id) {
+            let frame = self.signal.encode(connection.codec.as_ref(), message)?;
+            if connection.sender.unbounded_send(frame).is_err() {
+                self.connections.remove(&id);
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<T> PollSignal for ServerSignalHub<T>
+where
+    T: Send,
+{
+    fn poll(
+        &self,
+        request: &LongPollRequest,
+        waiter: Option<&UnboundedSender<()>>,
+    ) -> Vec<ServerSignalMessage> {
+        let mut state = self.lock();
+        let signal = &state.signal;
+        if !request.is_subscribed(&signal.name) {
+            return Vec::new();
+        }
+        match request.version(&signal.name) {
+            Some(version) if version == signal.version => {
+                if let Some(waiter) = waiter {
+                    state.waiters.retain(|waiter| !waiter.is_closed());
+                    state.waiters.push(waiter.clone());
+                }
+                Vec::new()
+            }
+            Some(version)
+                if version < signal.version
+                    && state
+                        .history
+                        .front()
+                        .is_some_and(|update| update.version <= version + 1) =>
+            {
+                state
+                    .history
+                    .iter()
+                    .filter(|update| update.version > version)
+                    .cloned()
+                    .map(ServerSignalMessage::from)
+                    .collect()
+            }
+            _ => vec![state.snapshot()],
+        }
+    }
+}
+
+impl<T> Clone for ServerSignalHub<T> {
+    fn clone(&self) -> Self {
+        ServerSignalHub {
+            inner: Arc::clone(&self.inner),
+        }
+    }
+}
+
+impl<T> fmt::Debug for ServerSignalHub<T>
+where
+    T: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let state = self.lock();
+        f.debug_struct("ServerSignalHub")
+            .field("name", &state.signal.name)
+            .field("value", &state.signal.value)
+            .field("connections", &state.connections.len())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::channel::mpsc;
+
+    use super::*;
+    use crate::fixtures::{client_update, received, Count};
+
+    fn poll(count: &ServerSignalHub<Count>, version: u64) -> Vec<ServerSignalMessage> {
+        let request = LongPollRequest::new().with_version("count", version);
+        PollSignal::poll(count, &request, None)
+    }
+
+    #[test]
+    fn poll_returns_missed_updates_from_history() {
+        let count = ServerSignalHub::<Count>::new("count").unwrap();
+        for value in 1..=3 {
+            count.with(|count| count.value = value).unwrap();
+        }
+
+        let versions: Vec<_> = poll(&count, 1)
+            .iter()
+            .map(|message| match message {
+                ServerSignalMessage::Update(update) => update.version(),
+                message => panic!("expected an update, got {message:?}"),
+            })
+            .collect();
+        assert_eq!(versions, [2, 3]);
+    }
+
+    #[test]
+    fn poll_sends_snapshot_after_history_overflow() {
+        let count = ServerSignalHub::<Count>::new("count").unwrap();
+        for value in 1..=HISTORY_LEN as i32 + 1 {
+            count.with(|count| count.value = value).unwrap();
+        }
+
+        // The update to version 1 has been dropped from the history
+        match poll(&count, 0).as_slice() {
+            [ServerSignalMessage::Snapshot(snapshot)] => {
+                assert_eq!(snapshot.version(), HISTORY_LEN as u64 + 1)
+            }
+            messages => panic!("expected a snapshot, got {messages:?}"),
+        }
+        assert_eq!(poll(&count, 1).len(), HISTORY_LEN);
+    }
+
+    #[test]
+    fn poll_sends_snapshot_to_new_and_future_clients() {
+        let count = ServerSignalHub::<Count>::new("count").unwrap();
+        count.with(|count| count.value = 1).unwrap();
+
+        let request = LongPollRequest::new();
+        assert!(matches!(
+            PollSignal::poll(&count, &request, None).as_slice(),
+            [ServerSignalMessage::Snapshot(_)]
+        ));
+        assert!(matches!(
+            poll(&count, 5).as_slice(),
+            [ServerSignalMessage::Snapshot(_)]
+        ));
+    }
+
+    #[test]
+    fn poll_sends_snapshot_after_snapshot_threshold() {
+        let count = ServerSignalHub::<Count>::new("count")
+            .unwrap()
+            .snapshot_threshold(0.0);
+        count.with(|count| count.value = 1).unwrap();
+
+        assert!(matches!(
+            poll(&count, 0).as_slice(),
+            [ServerSignalMessage::Snapshot(_)]
+        ));
+    }
+
+    #[test]
+    fn poll_registers_waiter_when_up_to_date() {
+        let count = ServerSignalHub::<Count>::new("count").unwrap();
+        let request = LongPollRequest::new().with_version("count", 0);
+        let (waiter, mut updated) = mpsc::unbounded();
+
+        assert!(PollSignal::poll(&count, &request, Some(&waiter)).is_empty());
+        assert!(PollSignal::poll(&count, &request, None).is_empty());
+        assert_eq!(count.waiter_count(), 1);
+
+        count.with(|count| count.value = 1).unwrap();
+        assert_eq!(updated.try_recv(), Ok(()));
+        assert_eq!(count.waiter_count(), 0);
+    }
+
+    #[test]
+    fn poll_ignores_unsubscribed_signals() {
+        let count = ServerSignalHub::<Count>::new("count").unwrap();
+        let request = LongPollRequest::new().with_subscriptions(["other"]);
+        assert!(PollSignal::poll(&count, &request, None).is_empty());
+    }
+
+    #[test]
+    fn apply_update_sends_accepted_update_to_every_connection() {
+        let count = ServerSignalHub::<Count>::new("count")
+            .unwrap()
+            .writable(|_| true);
+        let (tx, mut rx) = mpsc::unbounded();
+        let id = count.connect(tx).unwrap();
+        let (other_tx, mut other_rx) = mpsc::unbounded();
+        count.connect(other_tx).unwrap();
+        count.with(|count| count.value = 1).unwrap();
+        received(&mut rx);
+        received(&mut other_rx);
+
+        assert!(count.apply_update(id, &client_update(1, 1, 5)).unwrap());
+        assert_eq!(count.get(), Count { value: 5 });
+        for rx in [&mut rx, &mut other_rx] {
+            match received(rx).as_slice() {
+                [ServerSignalMessage::Update(update)] => assert_eq!(update.version(), 2),
+                messages => panic!("expected an update, got {messages:?}"),
+            }
+        }
+    }
+
+    #[test]
+    fn apply_update_answers_rejection_to_writer_only() {
+        let count = ServerSignalHub::<Count>::new("count")
+            .unwrap()
+            .writable(|_| true);
+        let (tx, mut rx) = mpsc::unbounded();
+        let id = count.connect(tx).unwrap();
+        let (other_tx, mut other_rx) = mpsc::unbounded();
+        count.connect(other_tx).unwrap();
+        count.with(|count| count.value = 1).unwrap();
+        received(&mut rx);
+        received(&mut other_rx);
+
+        assert!(!count.apply_update(id, &client_update(0, 0, 5)).unwrap());
+        assert_eq!(count.get(), Count { value: 1 });
+        match received(&mut rx).as_slice() {
+            [ServerSignalMessage::Snapshot(snapshot)] => {
+                assert_eq!(snapshot.version(), 1);
+                assert_eq!(snapshot.value(), &serde_json::json!({ "value": 1 }));
+            }
+            messages => panic!("expected a snapshot, got {messages:?}"),
+        }
+        // The rejection is only sent to the connection which wrote the update
+        assert!(received(&mut other_rx).is_empty());
+    }
+
+    #[test]
+    fn apply_update_answers_unchanged_value_with_snapshot() {
+        let count = ServerSignalHub::<Count>::new("count")
+            .unwrap()
+            .writable(|_| true);
+        let (tx, mut rx) = mpsc::unbounded();
+        let id = count.connect(tx).unwrap();
+        received(&mut rx);
+
+        assert!(count.apply_update(id, &client_update(0, 1, 0)).unwrap());
+        match received(&mut rx).as_slice() {
+            [ServerSignalMessage::Snapshot(snapshot)] => assert_eq!(snapshot.version(), 0),
+            messages => panic!("expected a snapshot, got {messages:?}"),
+        }
+    }
+
+    #[test]
+    fn diffed_hub_sends_field_diffs() {
+        let count = ServerSignalHub::<Count>::new("count").unwrap().diffed();
+        let (tx, mut rx) = mpsc::unbounded();
+        count.connect(tx).unwrap();
+        received(&mut rx);
+
+        count.with(|count| count.value = 2).unwrap();
+        match received(&mut rx).as_slice() {
+            [ServerSignalMessage::Update(update)] => {
+                assert_eq!(update.version(), 1);
+                assert_eq!(
+                    serde_json::to_value(update.patch()).unwrap(),
+                    serde_json::json!([{ "op": "add", "path": "/value", "value": 2 }])
+                );
+            }
+            messages => panic!("expected an update, got {messages:?}"),
+        }
+        assert_eq!(count.json_value(), serde_json::json!({ "value": 2 }));
+    }
+
+    #[test]
+    fn panicking_update_does_not_poison_hub() {
+        let count = ServerSignalHub::<Count>::new("count").unwrap();
+        let (tx, mut rx) = mpsc::unbounded();
+        let id = count.connect(tx).unwrap();
+        received(&mut rx);
+
+        let result = std::panic::catch_unwind(|| {
+            count
+                .with(|count| {
+                    count.value = 1;
+                    panic!("bad update");
+                })
+                .unwrap();
+        });
+        assert!(result.is_err());
+
+        let (other_tx, _other_rx) = mpsc::unbounded();
+        count.connect(other_tx).unwrap();
+        count.with(|count| count.value += 1).unwrap();
+        match received(&mut rx).as_slice() {
+            [ServerSignalMessage::Update(update)] => {
+                assert_eq!(update.version(), 1);
+                assert_eq!(count.json_value(), serde_json::json!({ "value": 2 }));
+            }
+            messages => panic!("expected an update, got {messages:?}"),
+        }
+        assert!(count.disconnect(id));
+    }
+
+    #[test]
+    fn throttled_changes_are_held_until_flush() {
+        let count = ServerSignalHub::<Count>::new("count")
+            .unwrap()
+            .throttle(Duration::from_secs(60));
+        let (tx, mut rx) = mpsc::unbounded();
+        count.connect(tx).unwrap();
+        received(&mut rx);
+
+        count.with(|count| count.value = 1).unwrap();
+        assert_eq!(received(&mut rx).len(), 1);
+
+        // The last changes before a quiet period are only sent by flushing
+        count.with(|count| count.value = 2).unwrap();
+        count.with(|count| count.value = 3).unwrap();
+        assert!(received(&mut rx).is_empty());
+
+        count.flush().unwrap();
+        match received(&mut rx).as_slice() {
+            [ServerSignalMessage::Update(update)] => assert_eq!(update.version(), 2),
+            messages => panic!("expected an update, got {messages:?}"),
+        }
+        assert_eq!(count.json_value(), serde_json::json!({ "value": 3 }));
+        count.flush().unwrap();
+        assert!(received(&mut rx).is_empty());
+    }
+}
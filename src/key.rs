@@ -0,0 +1,67 @@
+/// A typed key binding a signal name to its value type.
+///
+/// Keys are shared between the client and server, so both sides agree on the name and type
+/// of a signal, and a typo or type mismatch becomes a compile error.
+/// Keys are usually declared with [`signal_key!`](crate::signal_key), and passed to
+/// [`create_keyed_server_signal`](crate::create_keyed_server_signal) on the client,
+/// and `ServerSignal::from_key` or `ServerSignalHub::from_key` on the server.
+///
+/// # Example
+///
+/// ```
+/// # use serde::{Deserialize, Serialize};
+/// use leptos_server_signal::SignalKey;
+///
+/// #[derive(Clone, Default, Serialize, Deserialize)]
+/// pub struct Count {
+///     pub value: i32,
+/// }
+///
+/// pub struct Counter;
+///
+/// impl SignalKey for Counter {
+///     type Value = Count;
+///     const NAME: &'static str = "counter";
+/// }
+/// ```
+pub trait SignalKey {
+    /// The type of the signal's value.
+    type Value;
+
+    /// The name identifying the signal over the connection.
+    const NAME: &'static str;
+}
+
+/// Declares unit structs implementing [`SignalKey`](crate::SignalKey).
+///
+/// # Example
+///
+/// ```
+/// # use serde::{Deserialize, Serialize};
+/// # #[derive(Clone, Default, Serialize, Deserialize)]
+/// # pub struct Count {
+/// #     pub value: i32,
+/// # }
+/// # #[derive(Clone, Default, Serialize, Deserialize)]
+/// # pub struct Messages(Vec<String>);
+/// leptos_server_signal::signal_key! {
+///     /// The shared counter.
+///     pub Counter: Count = "counter";
+///     pub Chat: Messages = "chat";
+/// }
+/// ```
+#[macro_export]
+macro_rules! signal_key {
+    ($($(#[$meta:meta])* $vis:vis $key:ident: $value:ty = $name:literal);* $(;)?) => {
+        $(
+            $(#[$meta])*
+            #[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+            $vis struct $key;
+
+            impl $crate::SignalKey for $key {
+                type Value = $value;
+                const NAME: &'static str = $name;
+            }
+        )*
+    };
+}
@@ -1,34 +1,94 @@
 #![doc = include_str!("../README.md")]
 
 use std::borrow::Cow;
+use std::collections::HashMap;
+use std::fmt;
+use std::time::Duration;
 
 use json_patch::Patch;
-use leptos::prelude::{signal, ReadSignal};
+use leptos::prelude::{signal, ReadSignal, RwSignal, Signal};
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use wasm_bindgen::JsValue;
-use web_sys::WebSocket;
+use web_sys::{EventSource, WebSocket};
+
+mod codec;
+mod delayed;
+mod diff;
+mod key;
+mod reconnect;
+#[cfg(any(target_arch = "wasm32", test))]
+mod versions;
+pub use crate::codec::*;
+pub use crate::delayed::*;
+#[doc(hidden)]
+pub use crate::diff::__private;
+pub use crate::diff::Diff;
+pub use crate::key::*;
+pub use crate::reconnect::*;
+#[cfg(feature = "derive")]
+pub use leptos_server_signal_macro::Diff;
+
+cfg_if::cfg_if! {
+    if #[cfg(feature = "compression")] {
+        mod compression;
+        pub use crate::compression::*;
+    }
+}
+
+cfg_if::cfg_if! {
+    if #[cfg(feature = "ssr")] {
+        mod collection;
+        mod event_stream;
+        #[cfg(test)]
+        mod fixtures;
+        mod hub;
+        mod long_poll;
+        mod server;
+        mod state;
+        mod strategy;
+        mod subscription;
+        pub use crate::collection::*;
+        pub use crate::event_stream::*;
+        pub use crate::hub::*;
+        pub use crate::long_poll::*;
+        pub use crate::server::*;
+        pub use crate::strategy::*;
+        pub use crate::subscription::*;
+    }
+}
 
 cfg_if::cfg_if! {
     if #[cfg(all(feature = "actix", feature = "ssr"))] {
         mod actix;
-        pub use crate::actix::*;
     }
 }
 
 cfg_if::cfg_if! {
     if #[cfg(all(feature = "axum", feature = "ssr"))] {
         mod axum;
-        pub use crate::axum::*;
+    }
+}
+
+cfg_if::cfg_if! {
+    if #[cfg(all(feature = "poem", feature = "ssr"))] {
+        mod poem;
+    }
+}
+
+cfg_if::cfg_if! {
+    if #[cfg(all(feature = "tungstenite", feature = "ssr"))] {
+        mod tungstenite;
     }
 }
 
 /// A server signal update containing the signal type name and json patch.
 ///
-/// This is whats sent over the websocket, and is used to patch the signal if the type name matches.
+/// This is sent over the websocket as a [`ServerSignalMessage`], and is used to patch the signal if the type name matches.
 #[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub struct ServerSignalUpdate {
     name: Cow<'static, str>,
+    version: u64,
     patch: Patch,
 }
 
@@ -47,6 +107,7 @@ impl ServerSignalUpdate {
         let patch = json_patch::diff(&left, &right);
         Ok(ServerSignalUpdate {
             name: name.into(),
+            version: 0,
             patch,
         })
     }
@@ -56,9 +117,574 @@ impl ServerSignalUpdate {
         let patch = json_patch::diff(old, new);
         ServerSignalUpdate {
             name: name.into(),
+            version: 0,
+            patch,
+        }
+    }
+
+    /// Creates a new [`ServerSignalUpdate`] from a json patch.
+    pub fn new_from_patch(name: impl Into<Cow<'static, str>>, patch: Patch) -> Self {
+        ServerSignalUpdate {
+            name: name.into(),
+            version: 0,
             patch,
         }
     }
+
+    /// Sets the version of the signal after this update is applied.
+    ///
+    /// Versions increase by one for each update, allowing clients to detect missed updates.
+    pub fn with_version(mut self, version: u64) -> Self {
+        self.version = version;
+        self
+    }
+
+    /// Returns the version of the signal after this update is applied.
+    pub fn version(&self) -> u64 {
+        self.version
+    }
+
+    /// Returns the json patch of this update.
+    pub fn patch(&self) -> &Patch {
+        &self.patch
+    }
+
+    /// Applies the patch to a copy of `json`, returning the patched json and its value as `T`.
+    ///
+    /// Returns `None` if the patch cannot be applied, or the result is not a valid `T`.
+    #[cfg(feature = "ssr")]
+    pub(crate) fn apply_to<T>(&self, json: &Value) -> Option<(Value, T)>
+    where
+        T: for<'de> Deserialize<'de>,
+    {
+        let mut json = json.clone();
+        json_patch::patch(&mut json, &self.patch).ok()?;
+        let value = serde_json::from_value(json.clone()).ok()?;
+        Some((json, value))
+    }
+}
+
+/// Decides whether an update written by the client is accepted by a server signal.
+#[cfg(feature = "ssr")]
+pub(crate) struct Validator<T>(std::sync::Arc<dyn Fn(&T) -> bool + Send + Sync>);
+
+#[cfg(feature = "ssr")]
+impl<T> Validator<T> {
+    pub(crate) fn new(validate: impl Fn(&T) -> bool + Send + Sync + 'static) -> Self {
+        Validator(std::sync::Arc::new(validate))
+    }
+
+    pub(crate) fn validate(&self, value: &T) -> bool {
+        (self.0)(value)
+    }
+}
+
+#[cfg(feature = "ssr")]
+impl<T> Clone for Validator<T> {
+    fn clone(&self) -> Self {
+        Validator(std::sync::Arc::clone(&self.0))
+    }
+}
+
+#[cfg(feature = "ssr")]
+impl<T> std::fmt::Debug for Validator<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("Validator")
+    }
+}
+
+/// Limits how often changes to a server signal are sent.
+#[cfg(feature = "ssr")]
+#[derive(Clone, Debug, Default)]
+pub(crate) struct Throttle {
+    interval: Option<Duration>,
+    last_sent: Option<std::time::Instant>,
+    pending: bool,
+}
+
+#[cfg(feature = "ssr")]
+impl Throttle {
+    pub(crate) fn new(interval: Duration) -> Self {
+        Throttle {
+            interval: Some(interval),
+            ..Default::default()
+        }
+    }
+
+    /// Returns `true` if a change can be sent now, otherwise marking it as pending.
+    pub(crate) fn ready(&mut self) -> bool {
+        match (self.interval, self.last_sent) {
+            (Some(interval), Some(last_sent)) if last_sent.elapsed() < interval => {
+                self.pending = true;
+                false
+            }
+            _ => true,
+        }
+    }
+
+    /// Records that all changes have been sent.
+    pub(crate) fn sent(&mut self) {
+        self.last_sent = Some(std::time::Instant::now());
+        self.pending = false;
+    }
+
+    pub(crate) fn is_pending(&self) -> bool {
+        self.pending
+    }
+}
+
+/// A server signal snapshot containing the signal type name and its full json value.
+///
+/// This is sent when a connection joins, and replaces the client's value entirely.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ServerSignalSnapshot {
+    name: Cow<'static, str>,
+    version: u64,
+    value: Value,
+}
+
+impl ServerSignalSnapshot {
+    /// Creates a new [`ServerSignalSnapshot`] from an instance of `T`.
+    pub fn new<T>(name: impl Into<Cow<'static, str>>, value: &T) -> Result<Self, serde_json::Error>
+    where
+        T: Serialize,
+    {
+        Ok(ServerSignalSnapshot {
+            name: name.into(),
+            version: 0,
+            value: serde_json::to_value(value)?,
+        })
+    }
+
+    /// Creates a new [`ServerSignalSnapshot`] from a json value.
+    pub fn new_from_json(name: impl Into<Cow<'static, str>>, value: Value) -> Self {
+        ServerSignalSnapshot {
+            name: name.into(),
+            version: 0,
+            value,
+        }
+    }
+
+    /// Sets the version of the signal this snapshot was taken at.
+    pub fn with_version(mut self, version: u64) -> Self {
+        self.version = version;
+        self
+    }
+
+    /// Returns the version of the signal this snapshot was taken at.
+    pub fn version(&self) -> u64 {
+        self.version
+    }
+
+    /// Returns the json value of this snapshot.
+    pub fn value(&self) -> &Value {
+        &self.value
+    }
+}
+
+/// A batch of messages for one or more signals, which the client applies together.
+///
+/// The client applies the messages of a batch synchronously, one after another, while handling
+/// a single frame. Effects only run once the frame has been handled, so they see all of the
+/// messages applied rather than some of them.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ServerSignalBatch {
+    messages: Vec<ServerSignalMessage>,
+}
+
+impl ServerSignalBatch {
+    /// Creates a new empty [`ServerSignalBatch`].
+    pub fn new() -> Self {
+        ServerSignalBatch::default()
+    }
+
+    /// Adds a message to the end of the batch.
+    pub fn push(&mut self, message: impl Into<ServerSignalMessage>) {
+        self.messages.push(message.into());
+    }
+
+    /// Returns the messages in the batch, in the order they are applied.
+    pub fn messages(&self) -> &[ServerSignalMessage] {
+        &self.messages
+    }
+
+    /// Consumes the batch, returning its messages.
+    pub fn into_messages(self) -> Vec<ServerSignalMessage> {
+        self.messages
+    }
+
+    /// Returns the number of messages in the batch.
+    pub fn len(&self) -> usize {
+        self.messages.len()
+    }
+
+    /// Returns `true` if the batch contains no messages.
+    pub fn is_empty(&self) -> bool {
+        self.messages.is_empty()
+    }
+}
+
+/// A message sent from the server to the client.
+///
+/// Updates are applied as json patches on top of the client's value,
+/// while snapshots replace the client's value entirely.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ServerSignalMessage {
+    /// A json patch to apply to the signal.
+    Update(ServerSignalUpdate),
+    /// The full value of the signal.
+    Snapshot(ServerSignalSnapshot),
+    /// Several messages applied together.
+    Batch(ServerSignalBatch),
+}
+
+impl ServerSignalMessage {
+    /// Returns the name of the signal this message is for, or `None` for a batch.
+    pub fn name(&self) -> Option<&str> {
+        match self {
+            ServerSignalMessage::Update(update) => Some(&update.name),
+            ServerSignalMessage::Snapshot(snapshot) => Some(&snapshot.name),
+            ServerSignalMessage::Batch(_) => None,
+        }
+    }
+
+    /// Returns the version of the signal after this message is applied, or `None` for a batch.
+    pub fn version(&self) -> Option<u64> {
+        match self {
+            ServerSignalMessage::Update(update) => Some(update.version),
+            ServerSignalMessage::Snapshot(snapshot) => Some(snapshot.version),
+            ServerSignalMessage::Batch(_) => None,
+        }
+    }
+
+    /// Applies the message to a json document.
+    ///
+    /// Batches are split into their messages before being applied, and are ignored here.
+    #[allow(dead_code)]
+    fn apply(&self, doc: &mut Value) -> Result<(), json_patch::PatchError> {
+        match self {
+            ServerSignalMessage::Update(update) => json_patch::patch(doc, &update.patch),
+            ServerSignalMessage::Snapshot(snapshot) => {
+                *doc = snapshot.value.clone();
+                Ok(())
+            }
+            ServerSignalMessage::Batch(_) => Ok(()),
+        }
+    }
+}
+
+impl From<ServerSignalUpdate> for ServerSignalMessage {
+    fn from(update: ServerSignalUpdate) -> Self {
+        ServerSignalMessage::Update(update)
+    }
+}
+
+impl From<ServerSignalSnapshot> for ServerSignalMessage {
+    fn from(snapshot: ServerSignalSnapshot) -> Self {
+        ServerSignalMessage::Snapshot(snapshot)
+    }
+}
+
+impl From<ServerSignalBatch> for ServerSignalMessage {
+    fn from(batch: ServerSignalBatch) -> Self {
+        ServerSignalMessage::Batch(batch)
+    }
+}
+
+/// A message sent from the client to the server.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ClientSignalMessage {
+    /// Requests a snapshot of the signal, sent when the client detects a missed update.
+    Resync {
+        /// The name of the signal.
+        name: Cow<'static, str>,
+    },
+    /// A json patch written by the client, with the version of the signal it was based on.
+    Update(ServerSignalUpdate),
+    /// Starts updates to the signal, sent when it is first read on the client.
+    ///
+    /// Servers which send the signal without waiting for a subscription may ignore this.
+    Subscribe {
+        /// The name of the signal.
+        name: Cow<'static, str>,
+    },
+    /// Stops updates to the signal, sent when its last reader on the client is disposed.
+    ///
+    /// Updates are sent again after the next subscribe or resync request.
+    Unsubscribe {
+        /// The name of the signal.
+        name: Cow<'static, str>,
+    },
+}
+
+impl ClientSignalMessage {
+    /// Returns the name of the signal this message is for.
+    pub fn name(&self) -> &str {
+        match self {
+            ClientSignalMessage::Resync { name }
+            | ClientSignalMessage::Subscribe { name }
+            | ClientSignalMessage::Unsubscribe { name } => name,
+            ClientSignalMessage::Update(update) => &update.name,
+        }
+    }
+}
+
+/// A long-polling request for the messages a client has missed.
+///
+/// Contains the last version the client received of each signal.
+/// Signals which are not included are sent a snapshot.
+/// If the request lists the signals the client reads, other signals are not sent.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LongPollRequest {
+    versions: HashMap<Cow<'static, str>, u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    subscriptions: Option<std::collections::HashSet<Cow<'static, str>>>,
+}
+
+impl LongPollRequest {
+    /// Creates a new empty [`LongPollRequest`], requesting a snapshot of every signal.
+    pub fn new() -> Self {
+        LongPollRequest::default()
+    }
+
+    /// Sets the last version received of a signal.
+    pub fn with_version(mut self, name: impl Into<Cow<'static, str>>, version: u64) -> Self {
+        self.versions.insert(name.into(), version);
+        self
+    }
+
+    /// Returns the last version received of a signal, or `None` if a snapshot is requested.
+    pub fn version(&self, name: &str) -> Option<u64> {
+        self.versions.get(name).copied()
+    }
+
+    /// Sets the signals the client reads, so that other signals are not sent.
+    pub fn with_subscriptions<N>(mut self, names: impl IntoIterator<Item = N>) -> Self
+    where
+        N: Into<Cow<'static, str>>,
+    {
+        self.subscriptions = Some(names.into_iter().map(Into::into).collect());
+        self
+    }
+
+    /// Returns `true` if the client reads a signal, which is the case for every signal
+    /// if the request does not list them.
+    pub fn is_subscribed(&self, name: &str) -> bool {
+        self.subscriptions
+            .as_ref()
+            .is_none_or(|subscriptions| subscriptions.contains(name))
+    }
+}
+
+/// The status of the websocket connection used by server signals.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub enum ConnectionStatus {
+    /// The websocket is being opened.
+    #[default]
+    Connecting,
+    /// The websocket is open, and server signals are being updated.
+    Open,
+    /// The websocket encountered an error, and is being closed.
+    Closing,
+    /// The websocket is closed, and will not be reconnected.
+    Closed,
+    /// The websocket was lost, and is being reconnected.
+    Reconnecting {
+        /// The number of reconnect attempts made so far, starting at 1.
+        attempt: u32,
+        /// The reason the last connection attempt failed, if known.
+        last_error: Option<String>,
+    },
+}
+
+impl ConnectionStatus {
+    /// Returns `true` if the websocket is open.
+    pub fn is_open(&self) -> bool {
+        matches!(self, ConnectionStatus::Open)
+    }
+}
+
+/// Returns a signal tracking the status of the provided websocket connection.
+///
+/// During SSR, this is always [`ConnectionStatus::Connecting`], matching the status on the client
+/// before the websocket opens.
+/// If no websocket has been provided on the client, this is always [`ConnectionStatus::Closed`].
+///
+/// # Example
+///
+/// ```ignore
+/// #[component]
+/// pub fn ConnectionBanner() -> impl IntoView {
+///     let status = leptos_server_signal::use_connection_status();
+///
+///     view! {
+///         <Show when=move || !status.get().is_open()>
+///             <p>"Reconnecting…"</p>
+///         </Show>
+///     }
+/// }
+/// ```
+pub fn use_connection_status() -> Signal<ConnectionStatus> {
+    cfg_if::cfg_if! {
+        if #[cfg(target_arch = "wasm32")] {
+            use leptos::prelude::use_context;
+
+            match use_context::<ServerSignalWebSocket>() {
+                Some(ws) => ws.status.into(),
+                None => Signal::stored(ConnectionStatus::Closed),
+            }
+        } else {
+            Signal::stored(ConnectionStatus::Connecting)
+        }
+    }
+}
+
+/// An error which occurred on the client while handling a message from the server,
+/// or sending an update to it.
+///
+/// When an error occurs, the affected signal keeps its last good value.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ClientError {
+    /// A websocket frame could not be decoded as a [`ServerSignalMessage`].
+    BadFrame {
+        /// The reason the frame could not be decoded.
+        reason: String,
+    },
+    /// A json patch could not be applied to a signal.
+    ///
+    /// A resync is requested for the signal, so it is replaced by a snapshot from the server.
+    PatchFailed {
+        /// The name of the signal.
+        name: Cow<'static, str>,
+        /// The reason the patch could not be applied.
+        reason: String,
+    },
+    /// The json value of a signal could not be deserialized into its type.
+    DeserializeFailed {
+        /// The name of the signal.
+        name: Cow<'static, str>,
+        /// The reason the value could not be deserialized.
+        reason: String,
+    },
+    /// An update written to a signal could not be sent to the server.
+    ///
+    /// This happens when connected through server-sent events or long-polling, which only receive
+    /// messages, or when the websocket fails to send. The written value is replaced by the next
+    /// message from the server.
+    UpdateNotSent {
+        /// The name of the signal.
+        name: Cow<'static, str>,
+    },
+}
+
+impl fmt::Display for ClientError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ClientError::BadFrame { reason } => write!(f, "bad server signal frame: {reason}"),
+            ClientError::PatchFailed { name, reason } => {
+                write!(f, "failed to patch server signal {name}: {reason}")
+            }
+            ClientError::DeserializeFailed { name, reason } => {
+                write!(f, "failed to deserialize server signal {name}: {reason}")
+            }
+            ClientError::UpdateNotSent { name } => {
+                write!(f, "failed to send update to server signal {name}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for ClientError {}
+
+/// Returns a signal containing the last error which occurred while handling messages from the server.
+///
+/// During SSR, or if no websocket has been provided, this is always `None`.
+///
+/// # Example
+///
+/// ```ignore
+/// #[component]
+/// pub fn ErrorBanner() -> impl IntoView {
+///     let error = leptos_server_signal::use_client_error();
+///
+///     view! {
+///         {move || error.get().map(|err| view! { <p class="error">{err.to_string()}</p> })}
+///     }
+/// }
+/// ```
+pub fn use_client_error() -> Signal<Option<ClientError>> {
+    cfg_if::cfg_if! {
+        if #[cfg(target_arch = "wasm32")] {
+            use leptos::prelude::use_context;
+
+            match use_context::<ServerSignalWebSocket>() {
+                Some(ws) => ws.error.into(),
+                None => Signal::stored(None),
+            }
+        } else {
+            Signal::stored(None)
+        }
+    }
+}
+
+/// Returns a signal containing counters for the messages queued for server signals which have not been read yet.
+///
+/// Signals whose queued messages keep being discarded are listed in [`DelayedUpdateStats::discarded`],
+/// which usually means the client reads them with a different name than the server sends them with.
+///
+/// During SSR, or if no websocket has been provided, this is always empty.
+///
+/// # Example
+///
+/// ```ignore
+/// let stats = leptos_server_signal::use_delayed_update_stats();
+/// Effect::new(move |_| {
+///     for (name, count) in stats.get().discarded {
+///         leptos::logging::warn!("{count} messages for {name} were never read");
+///     }
+/// });
+/// ```
+pub fn use_delayed_update_stats() -> Signal<DelayedUpdateStats> {
+    cfg_if::cfg_if! {
+        if #[cfg(target_arch = "wasm32")] {
+            use leptos::prelude::use_context;
+
+            match use_context::<ServerSignalWebSocket>() {
+                Some(ws) => ws.delayed_stats.into(),
+                None => Signal::stored(DelayedUpdateStats::default()),
+            }
+        } else {
+            Signal::stored(DelayedUpdateStats::default())
+        }
+    }
+}
+
+/// Sets limits on the messages queued for server signals which have not been read yet.
+///
+/// This should be called after the websocket, server-sent events or long-polling connection is provided.
+/// During SSR, this function is a no-op.
+///
+/// # Example
+///
+/// ```ignore
+/// leptos_server_signal::provide_websocket("ws://localhost:3000/ws").unwrap();
+/// leptos_server_signal::set_delayed_update_limits(
+///     DelayedUpdateLimits::new().max_age(Duration::from_secs(10)),
+/// );
+/// ```
+#[allow(unused_variables)]
+pub fn set_delayed_update_limits(limits: DelayedUpdateLimits) {
+    cfg_if::cfg_if! {
+        if #[cfg(target_arch = "wasm32")] {
+            use leptos::prelude::use_context;
+
+            if let Some(ws) = use_context::<ServerSignalWebSocket>() {
+                ws.delayed_updates.lock().unwrap().set_limits(limits);
+            }
+        }
+    }
 }
 
 /// Provides a websocket url for server signals, if there is not already one provided.
@@ -81,6 +707,7 @@ impl ServerSignalUpdate {
 /// ```
 #[allow(unused_variables)]
 pub fn provide_websocket(url: &str) -> Result<Option<WebSocket>, JsValue> {
+    share_initial_snapshots();
     provide_websocket_inner(url)
 }
 
@@ -88,6 +715,8 @@ pub fn provide_websocket(url: &str) -> Result<Option<WebSocket>, JsValue> {
 /// In case of a connection lost, the websocket will be reconnected after the specified
 /// timeout.
 ///
+/// This is a shorthand for [`provide_websocket_with_reconnect`] with a [fixed](ReconnectPolicy::fixed) delay.
+///
 /// During SSR, this function is a no-op and returns `Ok(None)`.
 /// During CSR, if this function returns `Ok`, then the `Option` will always be `Some`.
 ///
@@ -111,74 +740,732 @@ pub fn provide_websocket_with_retry(
     url: &str,
     timeout_in_ms: i32,
 ) -> Result<Option<WebSocket>, JsValue> {
-    let ws = provide_websocket_inner(url);
-    if let Ok(Some(ref ws)) = ws {
-        add_retry_timeout(ws, timeout_in_ms);
-    }
-    ws
+    let delay = Duration::from_millis(timeout_in_ms.max(0) as u64);
+    provide_websocket_with_reconnect(url, ReconnectPolicy::fixed(delay))
 }
 
-/// Creates a signal which is controlled by the server.
+/// Provides a websocket url for server signals, if there is not already one provided.
+/// Whenever the connection is closed, the websocket is reconnected according to the [`ReconnectPolicy`].
 ///
-/// This signal is initialized as T::default, is read-only on the client, and is updated through json patches
-/// sent through a websocket connection.
+/// After reconnecting, every server signal subscribes again and waits for a snapshot from the new connection,
+/// so they are brought up to date with changes made while the connection was lost.
 ///
-/// # Example
+/// During SSR, this function is a no-op and returns `Ok(None)`.
+/// During CSR, if this function returns `Ok`, then the `Option` will always be `Some`.
 ///
-/// ```
-/// # use leptos::prelude::*;
-/// # use serde::{Deserialize, Serialize};
-/// # use leptos_server_signal::create_server_signal;
+/// Note, the server should have a route to handle this websocket.
 ///
-/// #[derive(Clone, Default, Serialize, Deserialize)]
-/// pub struct Count {
-///     pub value: i32,
-/// }
+/// # Example
 ///
+/// ```ignore
 /// #[component]
 /// pub fn App() -> impl IntoView {
-///     // Create server signal
-///     let count = create_server_signal::<Count>("counter");
+///     // Provide websocket connection
+///     leptos_server_signal::provide_websocket_with_reconnect(
+///         "ws://localhost:3000/ws",
+///         ReconnectPolicy::new()
+///             .initial_delay(Duration::from_millis(500))
+///             .max_attempts(10),
+///     ).unwrap();
+///
+///     // ...
+/// }
+/// ```
+pub fn provide_websocket_with_reconnect(
+    url: &str,
+    policy: ReconnectPolicy,
+) -> Result<Option<WebSocket>, JsValue> {
+    share_initial_snapshots();
+    let ws = provide_websocket_inner(url);
+    if let Ok(Some(_)) = ws {
+        set_reconnect_policy(policy);
+    }
+    ws
+}
+
+/// Provides a websocket url for server signals, if there is not already one provided,
+/// falling back to long-polling if the websocket fails to open.
+///
+/// Once the websocket has opened, the fallback is no longer used, and lost connections
+/// are handled by the [reconnect policy](provide_websocket_with_reconnect) if one is set.
+///
+/// During SSR, this function is a no-op and returns `Ok(None)`.
+/// During CSR, if this function returns `Ok`, then the `Option` will always be `Some`.
+///
+/// Note, the server should have a route to handle this websocket, and a route answering
+/// long-polling requests with a [`LongPoll`](crate::LongPoll) at `long_poll_url`.
+///
+/// # Example
+///
+/// ```ignore
+/// #[component]
+/// pub fn App() -> impl IntoView {
+///     // Provide websocket connection, with a long-polling fallback
+///     leptos_server_signal::provide_websocket_with_long_poll(
+///         "ws://localhost:3000/ws",
+///         "http://localhost:3000/poll",
+///     ).unwrap();
+///
+///     // ...
+/// }
+/// ```
+pub fn provide_websocket_with_long_poll(
+    url: &str,
+    long_poll_url: &str,
+) -> Result<Option<WebSocket>, JsValue> {
+    share_initial_snapshots();
+    let ws = provide_websocket_inner(url);
+    if let Ok(Some(_)) = ws {
+        set_long_poll_fallback(long_poll_url);
+    }
+    ws
+}
+
+/// Provides a long-polling url for server signals, if there is not already one provided.
+///
+/// The client repeatedly requests the messages it has missed since the last versions it received,
+/// for networks where neither websockets nor server-sent events work.
+/// Failed requests are retried according to the [reconnect policy](provide_websocket_with_reconnect),
+/// or the default [`ReconnectPolicy`] if none is set.
+///
+/// Like server-sent events, long-polling only receives messages from the server,
+/// so [writable](create_server_rw_signal) signals cannot send updates.
+///
+/// During SSR, this function is a no-op.
+///
+/// Note, the server should have a route answering requests with a [`LongPoll`](crate::LongPoll) at this url.
+///
+/// # Example
+///
+/// ```ignore
+/// #[component]
+/// pub fn App() -> impl IntoView {
+///     // Provide long-polling connection
+///     leptos_server_signal::provide_long_poll("http://localhost:3000/poll").unwrap();
+///
+///     // ...
+/// }
+/// ```
+pub fn provide_long_poll(url: &str) -> Result<(), JsValue> {
+    share_initial_snapshots();
+    provide_long_poll_inner(url)
+}
+
+/// Provides a server-sent events url for server signals, if there is not already one provided.
+///
+/// This can be used instead of [`provide_websocket`] where websockets are unavailable,
+/// such as behind proxies which do not support them. The browser reconnects automatically
+/// when the connection is lost.
+///
+/// Server-sent events only flow from the server to the client, so [writable](create_server_rw_signal)
+/// signals cannot send updates, and resyncs are requested by reopening the connection,
+/// which should start with a snapshot of every signal.
+///
+/// During SSR, this function is a no-op and returns `Ok(None)`.
+/// During CSR, if this function returns `Ok`, then the `Option` will always be `Some`.
+///
+/// Note, the server should have a route returning an [`EventStream`](crate::EventStream) at this url.
+///
+/// # Example
+///
+/// ```ignore
+/// #[component]
+/// pub fn App() -> impl IntoView {
+///     // Provide server-sent events connection
+///     leptos_server_signal::provide_event_source("http://localhost:3000/events").unwrap();
+///
+///     // ...
+/// }
+/// ```
+pub fn provide_event_source(url: &str) -> Result<Option<EventSource>, JsValue> {
+    share_initial_snapshots();
+    provide_event_source_inner(url)
+}
+
+/// Provides the current value of a server signal while rendering on the server,
+/// so the client starts from it instead of `T::default()`.
+///
+/// The snapshot is used as the initial value of the server signal with the same name during SSR,
+/// and is serialized into the hydration payload, so the client hydrates with the same value
+/// and only applies updates sent after its version.
+///
+/// This should be called on the server before the app is rendered, such as in the
+/// `additional_context` of the server integration, with snapshots from
+/// [`ServerSignal::snapshot`](crate::ServerSignal::snapshot) or
+/// [`ServerSignalHub::snapshot`](crate::ServerSignalHub::snapshot).
+///
+/// The snapshots are serialized when the connection is provided, such as with [`provide_websocket`].
+/// Entries of the hydration payload are read back in the order they were written, so the connection
+/// must be provided at the same point while rendering on the server and hydrating on the client,
+/// before any server signal is created, usually at the top of the root component.
+///
+/// # Example
+///
+/// ```ignore
+/// let routes = leptos_routes_with_context(
+///     &leptos_options,
+///     routes,
+///     move || leptos_server_signal::provide_initial_snapshot(count.snapshot()),
+///     move || shell(leptos_options.clone()),
+/// );
+/// ```
+pub fn provide_initial_snapshot(snapshot: ServerSignalSnapshot) {
+    use leptos::prelude::{provide_context, use_context};
+
+    let snapshots = use_context::<InitialSnapshots>().unwrap_or_else(|| {
+        let snapshots = InitialSnapshots::default();
+        provide_context(snapshots.clone());
+        snapshots
+    });
+    snapshots
+        .0
+        .lock()
+        .unwrap()
+        .insert(snapshot.name.clone(), snapshot);
+}
+
+/// The snapshots provided with [`provide_initial_snapshot`] as a context in Leptos.
+#[derive(Clone, Debug, Default)]
+struct InitialSnapshots(
+    std::sync::Arc<std::sync::Mutex<HashMap<Cow<'static, str>, ServerSignalSnapshot>>>,
+);
+
+/// The snapshots provided with [`provide_initial_snapshot`], as read back from the hydration payload on the client.
+#[derive(Clone, Debug, Default)]
+struct SharedSnapshots(std::sync::Arc<HashMap<Cow<'static, str>, ServerSignalSnapshot>>);
+
+/// Shares the snapshots provided with [`provide_initial_snapshot`] with the client through the hydration payload.
+///
+/// This is called by the functions providing a connection, so the payload is written once per render,
+/// and is empty if no snapshot was provided. Entries of the hydration payload are read back in the order
+/// they were written, so the connection must be provided at the same point while rendering on the server
+/// and hydrating on the client, before any server signal is created.
+fn share_initial_snapshots() {
+    use leptos::prelude::{provide_context, use_context, SharedValue};
+
+    if use_context::<SharedSnapshots>().is_some() {
+        return;
+    }
+    let snapshots = SharedValue::new(|| {
+        use_context::<InitialSnapshots>()
+            .map(|snapshots| snapshots.0.lock().unwrap().clone())
+            .unwrap_or_default()
+    })
+    .into_inner();
+    provide_context(SharedSnapshots(std::sync::Arc::new(snapshots)));
+}
+
+/// Returns the snapshot provided for a server signal with [`provide_initial_snapshot`].
+fn initial_snapshot(name: &str) -> Option<ServerSignalSnapshot> {
+    use leptos::prelude::use_context;
+
+    use_context::<SharedSnapshots>().and_then(|snapshots| snapshots.0.get(name).cloned())
+}
+
+/// Returns the value of a snapshot, or `None` if there is none or it cannot be deserialized.
+fn snapshot_value<T>(snapshot: Option<&ServerSignalSnapshot>) -> Option<T>
+where
+    T: for<'de> Deserialize<'de>,
+{
+    snapshot.and_then(|snapshot| T::deserialize(&snapshot.value).ok())
+}
+
+/// Returns the value of a snapshot, or `T::default()` if there is none or it cannot be deserialized.
+fn initial_value<T>(snapshot: Option<&ServerSignalSnapshot>) -> T
+where
+    T: Default + for<'de> Deserialize<'de>,
+{
+    snapshot_value(snapshot).unwrap_or_default()
+}
+
+/// The key and signal of each row of a list.
+type Rows<K, T> = Vec<(K, ReadSignal<T>)>;
+
+/// Creates a signal for each row of a list of keyed values, returning the signals by key and the list of rows.
+fn initial_rows<K, T>(
+    values: impl IntoIterator<Item = (K, T)>,
+) -> (HashMap<K, RwSignal<T>>, Rows<K, T>)
+where
+    K: Clone + Eq + std::hash::Hash,
+    T: Send + Sync + 'static,
+{
+    values
+        .into_iter()
+        .map(|(key, value)| {
+            let row = RwSignal::new(value);
+            ((key.clone(), row), (key, row.read_only()))
+        })
+        .unzip()
+}
+
+/// Creates a signal which is controlled by the server.
+///
+/// This signal is initialized as T::default, or the value provided with [`provide_initial_snapshot`] during SSR,
+/// is read-only on the client, and is updated through json patches sent through a websocket connection.
+///
+/// # Example
+///
+/// ```
+/// # use leptos::prelude::*;
+/// # use serde::{Deserialize, Serialize};
+/// # use leptos_server_signal::create_server_signal;
+///
+/// #[derive(Clone, Default, Serialize, Deserialize)]
+/// pub struct Count {
+///     pub value: i32,
+/// }
+///
+/// #[component]
+/// pub fn App() -> impl IntoView {
+///     // Create server signal
+///     let count = create_server_signal::<Count>("counter");
+///
+///     view! {
+///         <h1>"Count: " {move || count.get().value.to_string()}</h1>
+///     }
+/// }
+/// ```
+#[allow(unused_variables)]
+pub fn create_server_signal<T>(name: impl Into<Cow<'static, str>>) -> ReadSignal<T>
+where
+    T: Send + Sync + Default + Serialize + for<'de> Deserialize<'de> + 'static,
+{
+    let name: Cow<'static, str> = name.into();
+    let snapshot = initial_snapshot(&name);
+    let (get, set) = signal(initial_value(snapshot.as_ref()));
+
+    cfg_if::cfg_if! {
+        if #[cfg(target_arch = "wasm32")] {
+            use leptos::prelude::{Get, Effect, Set};
+
+            if let Some((ws, signal)) = register_server_signal::<T>(name.clone(), snapshot) {
+                // Note: The leptos docs advise against doing this. It seems to work
+                // well in testing, and the primary caveats are around unnecessary
+                // updates firing, but our state synchronization already prevents
+                // that on the server side
+                Effect::new(move |_| {
+                    if let Some(new_value) = ws.deserialize_value(&name, signal.get()) {
+                        set.set(new_value);
+                    }
+                });
+            }
+        }
+    }
+
+    get
+}
+
+/// Creates a signal which is controlled by the server, for types which do not implement `Default`.
+///
+/// Like a Leptos `Resource`, this signal is `None` while loading, until the first snapshot is received
+/// from the server, or the value provided with [`provide_initial_snapshot`] during SSR.
+/// It is then updated through json patches like [`create_server_signal`].
+///
+/// # Example
+///
+/// ```
+/// # use leptos::prelude::*;
+/// # use serde::{Deserialize, Serialize};
+/// # use leptos_server_signal::create_optional_server_signal;
+///
+/// #[derive(Clone, Serialize, Deserialize)]
+/// pub struct User {
+///     pub name: String,
+/// }
+///
+/// #[component]
+/// pub fn App() -> impl IntoView {
+///     // Create server signal, which is `None` until the user is received
+///     let user = create_optional_server_signal::<User>("user");
+///
+///     view! {
+///         <h1>{move || user.get().map(|user| user.name).unwrap_or_else(|| "Loading...".to_string())}</h1>
+///     }
+/// }
+/// ```
+#[allow(unused_variables)]
+pub fn create_optional_server_signal<T>(name: impl Into<Cow<'static, str>>) -> ReadSignal<Option<T>>
+where
+    T: Send + Sync + Serialize + for<'de> Deserialize<'de> + 'static,
+{
+    let name: Cow<'static, str> = name.into();
+    let snapshot = initial_snapshot(&name);
+    let (get, set) = signal(snapshot_value(snapshot.as_ref()));
+
+    cfg_if::cfg_if! {
+        if #[cfg(target_arch = "wasm32")] {
+            use leptos::prelude::{Get, Effect, Set};
+
+            if let Some((ws, signal)) = register_server_json(name.clone(), snapshot, Value::Null) {
+                Effect::new(move |_| {
+                    let value = signal.get();
+                    // The value is not known until a snapshot has been received
+                    if !ws.is_synced(&name) {
+                        return;
+                    }
+                    if let Some(new_value) = ws.deserialize_value(&name, value) {
+                        set.set(Some(new_value));
+                    }
+                });
+            }
+        }
+    }
+
+    get
+}
+
+/// Creates a signal which is controlled by the server, and can be written to by the client.
+///
+/// This signal behaves like [`create_server_signal`], but changes made on the client are diffed
+/// and sent to the server as json patches through the websocket connection.
+/// The server decides whether to accept the change, and echoes accepted changes back to all clients.
+/// Rejected changes are reverted by a snapshot from the server.
+///
+/// # Example
+///
+/// ```
+/// # use leptos::prelude::*;
+/// # use serde::{Deserialize, Serialize};
+/// # use leptos_server_signal::create_server_rw_signal;
+///
+/// #[derive(Clone, Default, Serialize, Deserialize)]
+/// pub struct Count {
+///     pub value: i32,
+/// }
+///
+/// #[component]
+/// pub fn App() -> impl IntoView {
+///     // Create writable server signal
+///     let count = create_server_rw_signal::<Count>("counter");
+///
+///     view! {
+///         <button on:click=move |_| count.update(|count| count.value += 1)>
+///             "Count: " {move || count.get().value.to_string()}
+///         </button>
+///     }
+/// }
+/// ```
+#[allow(unused_variables)]
+pub fn create_server_rw_signal<T>(name: impl Into<Cow<'static, str>>) -> RwSignal<T>
+where
+    T: Send + Sync + Default + Serialize + for<'de> Deserialize<'de> + 'static,
+{
+    let name: Cow<'static, str> = name.into();
+    let snapshot = initial_snapshot(&name);
+
+    cfg_if::cfg_if! {
+        if #[cfg(target_arch = "wasm32")] {
+            use std::sync::{Arc, Mutex};
+            use leptos::prelude::{Get, GetUntracked, Effect, Set, With, WithUntracked};
+
+            let Some((ws, signal)) = register_server_signal::<T>(name.clone(), snapshot.clone()) else {
+                return RwSignal::new(initial_value(snapshot.as_ref()));
+            };
+            // Start from the server value if it has already been received, so the first
+            // run of the effect below does not send the default value to the server.
+            let value = RwSignal::new(serde_json::from_value(signal.get_untracked()).unwrap_or_default());
+
+            // The json value last sent to the server, until the server answers it.
+            // Changes made meanwhile are sent once it answers, so each update is written against
+            // the server's current version.
+            let pending: Arc<Mutex<Option<Value>>> = Default::default();
+
+            let effect_ws = ws.clone();
+            let effect_name = name.clone();
+            let effect_pending = Arc::clone(&pending);
+            Effect::new(move |_| {
+                let server_json = signal.get();
+                let sent = effect_pending.lock().unwrap().take();
+                if let Some(sent) = sent {
+                    if let Ok(local_json) = value.with_untracked(|value| serde_json::to_value(value)) {
+                        if local_json != sent {
+                            if effect_ws.send_update(effect_name.clone(), &server_json, &local_json) {
+                                *effect_pending.lock().unwrap() = Some(local_json);
+                            }
+                            return;
+                        }
+                    }
+                }
+                if let Some(new_value) = effect_ws.deserialize_value(&effect_name, server_json) {
+                    value.set(new_value);
+                }
+            });
+
+            // Changes which did not come from the server produce a non-empty diff,
+            // and are sent to the server to be confirmed.
+            Effect::new(move |_| {
+                let Ok(new_json) = value.with(|value| serde_json::to_value(value)) else {
+                    return;
+                };
+                let mut pending = pending.lock().unwrap();
+                if pending.is_none() && ws.send_update(name.clone(), &signal.get_untracked(), &new_json) {
+                    *pending = Some(new_json);
+                }
+            });
+
+            value
+        } else {
+            RwSignal::new(initial_value(snapshot.as_ref()))
+        }
+    }
+}
+
+/// Creates a signal which is controlled by the server, named and typed by a [`SignalKey`].
+///
+/// This is the same as [`create_server_signal`], but the key ensures the client and server
+/// agree on the signal's name and type.
+///
+/// # Example
+///
+/// ```
+/// # use leptos::prelude::*;
+/// # use serde::{Deserialize, Serialize};
+/// # use leptos_server_signal::create_keyed_server_signal;
+///
+/// #[derive(Clone, Default, Serialize, Deserialize)]
+/// pub struct Count {
+///     pub value: i32,
+/// }
+///
+/// leptos_server_signal::signal_key! {
+///     pub Counter: Count = "counter";
+/// }
+///
+/// #[component]
+/// pub fn App() -> impl IntoView {
+///     // Create server signal from its key
+///     let count = create_keyed_server_signal(Counter);
+///
+///     view! {
+///         <h1>"Count: " {move || count.get().value.to_string()}</h1>
+///     }
+/// }
+/// ```
+pub fn create_keyed_server_signal<K>(_key: K) -> ReadSignal<K::Value>
+where
+    K: SignalKey,
+    K::Value: Send + Sync + Default + Serialize + for<'de> Deserialize<'de> + 'static,
+{
+    create_server_signal(K::NAME)
+}
+
+/// Creates a signal which is controlled by the server, and can be written to by the client,
+/// named and typed by a [`SignalKey`].
+///
+/// This is the same as [`create_server_rw_signal`], but the key ensures the client and server
+/// agree on the signal's name and type.
+pub fn create_keyed_server_rw_signal<K>(_key: K) -> RwSignal<K::Value>
+where
+    K: SignalKey,
+    K::Value: Send + Sync + Default + Serialize + for<'de> Deserialize<'de> + 'static,
+{
+    create_server_rw_signal(K::NAME)
+}
+
+/// Creates a [`Store`](reactive_stores::Store) which is controlled by the server.
+///
+/// This behaves like [`create_server_signal`], but each change from the server is
+/// [patched](reactive_stores::Patch) into the store, notifying only the fields which changed.
+/// Views reading one field of a large value are not re-rendered when other fields change.
+///
+/// Changes made to the store on the client are not sent to the server.
+///
+/// # Example
+///
+/// ```
+/// # use leptos::prelude::*;
+/// # use reactive_stores::{Patch, Store};
+/// # use serde::{Deserialize, Serialize};
+/// # use leptos_server_signal::create_server_store;
+///
+/// #[derive(Clone, Default, Serialize, Deserialize, Store, Patch)]
+/// pub struct Dashboard {
+///     pub visitors: u32,
+///     pub messages: Vec<String>,
+/// }
+///
+/// #[component]
+/// pub fn App() -> impl IntoView {
+///     // Create server store
+///     let dashboard = create_server_store::<Dashboard>("dashboard");
+///
+///     view! {
+///         // Not re-rendered when messages change
+///         <h1>"Visitors: " {move || dashboard.visitors().get()}</h1>
+///     }
+/// }
+/// ```
+#[cfg(feature = "store")]
+#[allow(unused_variables)]
+pub fn create_server_store<T>(name: impl Into<Cow<'static, str>>) -> reactive_stores::Store<T>
+where
+    T: Send
+        + Sync
+        + Default
+        + Serialize
+        + for<'de> Deserialize<'de>
+        + reactive_stores::PatchField
+        + 'static,
+{
+    let name: Cow<'static, str> = name.into();
+    let snapshot = initial_snapshot(&name);
+    let store = reactive_stores::Store::new(initial_value(snapshot.as_ref()));
+
+    cfg_if::cfg_if! {
+        if #[cfg(target_arch = "wasm32")] {
+            use leptos::prelude::{Effect, Get};
+            use reactive_stores::Patch;
+
+            if let Some((ws, signal)) = register_server_signal::<T>(name.clone(), snapshot) {
+                Effect::new(move |_| {
+                    if let Some(new_value) = ws.deserialize_value(&name, signal.get()) {
+                        store.patch(new_value);
+                    }
+                });
+            }
+        }
+    }
+
+    store
+}
+
+/// Creates a list of signals, one per item of a server signal containing a list,
+/// such as a `ServerVec` on the server.
+///
+/// The returned signal contains each item's key and a signal of its value. It only changes when items
+/// are inserted, removed or reordered, while changes to an item only update that item's signal,
+/// so a `<For>` keyed by the item key re-renders only the affected rows.
+/// Keys should be unique, and only the first item with a duplicate key is kept.
+///
+/// # Example
+///
+/// ```
+/// # use leptos::prelude::*;
+/// # use serde::{Deserialize, Serialize};
+/// # use leptos_server_signal::create_server_vec;
+///
+/// #[derive(Clone, Default, PartialEq, Serialize, Deserialize)]
+/// pub struct Todo {
+///     pub id: u32,
+///     pub title: String,
+/// }
+///
+/// #[component]
+/// pub fn Todos() -> impl IntoView {
+///     let todos = create_server_vec("todos", |todo: &Todo| todo.id);
+///
+///     view! {
+///         <ul>
+///             <For each=move || todos.get() key=|(id, _)| *id let:todo>
+///                 <li>{move || todo.1.get().title}</li>
+///             </For>
+///         </ul>
+///     }
+/// }
+/// ```
+#[allow(unused_variables)]
+pub fn create_server_vec<T, K>(
+    name: impl Into<Cow<'static, str>>,
+    key: fn(&T) -> K,
+) -> ReadSignal<Vec<(K, ReadSignal<T>)>>
+where
+    T: PartialEq + Send + Sync + Serialize + for<'de> Deserialize<'de> + 'static,
+    K: Clone + Eq + std::hash::Hash + Send + Sync + 'static,
+{
+    let name: Cow<'static, str> = name.into();
+    let snapshot = initial_snapshot(&name);
+    let items: Vec<T> = initial_value(snapshot.as_ref());
+    let (rows, list) = initial_rows(items.into_iter().map(|item| (key(&item), item)));
+    let (get, set) = signal(list);
+
+    cfg_if::cfg_if! {
+        if #[cfg(target_arch = "wasm32")] {
+            use leptos::prelude::{Effect, Get, Owner};
+
+            if let Some((ws, signal)) = register_server_signal::<Vec<T>>(name.clone(), snapshot) {
+                let owner = Owner::current().unwrap_or_default();
+                let mut initial = Some(rows);
+                Effect::new(move |rows: Option<HashMap<K, RwSignal<T>>>| {
+                    let rows = rows.or_else(|| initial.take()).unwrap_or_default();
+                    match ws.deserialize_value::<Vec<T>>(&name, signal.get()) {
+                        Some(items) => sync_rows(&owner, rows, items.into_iter().map(|item| (key(&item), item)), set),
+                        None => rows,
+                    }
+                });
+            }
+        }
+    }
+
+    get
+}
+
+/// Creates a list of signals, one per entry of a server signal containing a map,
+/// such as a `ServerMap` on the server.
+///
+/// The returned signal contains each entry's key and a signal of its value, ordered by key.
+/// It only changes when entries are inserted or removed, while changes to an entry only update
+/// that entry's signal, so a `<For>` keyed by the entry key re-renders only the affected rows.
+///
+/// # Example
+///
+/// ```
+/// # use leptos::prelude::*;
+/// # use serde::{Deserialize, Serialize};
+/// # use leptos_server_signal::create_server_map;
+///
+/// #[derive(Clone, Default, PartialEq, Serialize, Deserialize)]
+/// pub struct User {
+///     pub online: bool,
+/// }
+///
+/// #[component]
+/// pub fn Users() -> impl IntoView {
+///     let users = create_server_map::<String, User>("users");
 ///
 ///     view! {
-///         <h1>"Count: " {move || count.get().value.to_string()}</h1>
+///         <For each=move || users.get() key=|(name, _)| name.clone() let:user>
+///             <p>{user.0} {move || if user.1.get().online { " (online)" } else { "" }}</p>
+///         </For>
 ///     }
 /// }
 /// ```
 #[allow(unused_variables)]
-pub fn create_server_signal<T>(name: impl Into<Cow<'static, str>>) -> ReadSignal<T>
+pub fn create_server_map<K, V>(
+    name: impl Into<Cow<'static, str>>,
+) -> ReadSignal<Vec<(K, ReadSignal<V>)>>
 where
-    T: Send + Sync + Default + Serialize + for<'de> Deserialize<'de> + 'static,
+    K: Clone
+        + Ord
+        + std::hash::Hash
+        + Send
+        + Sync
+        + Serialize
+        + for<'de> Deserialize<'de>
+        + 'static,
+    V: PartialEq + Send + Sync + Serialize + for<'de> Deserialize<'de> + 'static,
 {
     let name: Cow<'static, str> = name.into();
-    let (get, set) = signal(T::default());
+    let snapshot = initial_snapshot(&name);
+    let entries: std::collections::BTreeMap<K, V> = initial_value(snapshot.as_ref());
+    let (rows, list) = initial_rows(entries);
+    let (get, set) = signal(list);
 
     cfg_if::cfg_if! {
         if #[cfg(target_arch = "wasm32")] {
-            use leptos::prelude::{use_context, Get, Effect, RwSignal, Set};
-
-            let signal = RwSignal::new(serde_json::to_value(T::default()).unwrap());
-            if let Some(ServerSignalWebSocket { state_signals, .. }) = use_context::<ServerSignalWebSocket>() {
-                state_signals.lock().unwrap().insert(name.clone(), signal);
+            use std::collections::BTreeMap;
+            use leptos::prelude::{Effect, Get, Owner};
 
-                // Note: The leptos docs advise against doing this. It seems to work
-                // well in testing, and the primary caveats are around unnecessary
-                // updates firing, but our state synchronization already prevents
-                // that on the server side
-                Effect::new(move |_| {
-                    let name = name.clone();
-                    let new_value = serde_json::from_value(signal.get()).unwrap();
-                    set.set(new_value);
+            if let Some((ws, signal)) = register_server_signal::<BTreeMap<K, V>>(name.clone(), snapshot) {
+                let owner = Owner::current().unwrap_or_default();
+                let mut initial = Some(rows);
+                Effect::new(move |rows: Option<HashMap<K, RwSignal<V>>>| {
+                    let rows = rows.or_else(|| initial.take()).unwrap_or_default();
+                    match ws.deserialize_value::<BTreeMap<K, V>>(&name, signal.get()) {
+                        Some(entries) => sync_rows(&owner, rows, entries, set),
+                        None => rows,
+                    }
                 });
-
-            } else {
-                leptos::logging::error!(
-                    r#"server signal was used without a websocket being provided.
-
-Ensure you call `leptos_server_signal::provide_websocket("ws://localhost:3000/ws")` at the highest level in your app."#
-                );
             }
-
         }
     }
 
@@ -187,114 +1474,919 @@ Ensure you call `leptos_server_signal::provide_websocket("ws://localhost:3000/ws
 
 cfg_if::cfg_if! {
     if #[cfg(target_arch = "wasm32")] {
-        use std::collections::HashMap;
         use std::sync::{Arc, Mutex};
 
-        use leptos::prelude::{provide_context, RwSignal};
+        use leptos::prelude::{Owner, WriteSignal};
+
+        use crate::versions::{SignalVersions, VersionCheck};
+
+        use leptos::prelude::provide_context;
+
+        /// The connection used to receive server signals.
+        #[derive(Clone, Debug)]
+        enum Connection {
+            WebSocket(WebSocket),
+            EventSource(EventSource),
+            // The url requests are sent to
+            LongPoll(String),
+        }
 
         /// The websocket connection wrapper provided as a context in Leptos.
+        ///
+        /// This wraps a server-sent events connection instead if one was provided with [`provide_event_source`].
         #[derive(Clone, Debug)]
         pub struct ServerSignalWebSocket {
-            ws: WebSocket,
+            // Replaced when the websocket reconnects
+            connection: Arc<Mutex<Connection>>,
             // References to these are kept by the closure for the callback
             // onmessage callback on the websocket
-            state_signals: Arc<Mutex<HashMap<Cow<'static, str>, RwSignal<serde_json::Value>>>>,
+            state_signals: Arc<Mutex<HashMap<Cow<'static, str>, StateSignal>>>,
             // When the websocket is first established, the leptos may not have
             // completed the traversal that sets up all of the state signals.
             // Without that, we don't have a base state to apply the patches to,
-            // and therefore we must keep a record of the patches to apply after
+            // and therefore we must keep a record of the messages to apply after
             // the state has been set up.
-            delayed_updates: Arc<Mutex<HashMap<Cow<'static, str>, Vec<Patch>>>>,
+            delayed_updates: Arc<Mutex<DelayedUpdates>>,
+            delayed_stats: RwSignal<DelayedUpdateStats>,
+            // The last version received for each signal, used to detect missed updates.
+            versions: Arc<Mutex<SignalVersions>>,
+            status: RwSignal<ConnectionStatus>,
+            error: RwSignal<Option<ClientError>>,
+            reconnect: Arc<Mutex<Option<ReconnectPolicy>>>,
+            // Negotiated through the websocket subprotocol when the connection is opened
+            codec: Arc<Mutex<Arc<dyn Codec>>>,
+            // Switched to if the websocket closes before it has opened
+            long_poll_fallback: Arc<Mutex<Option<String>>>,
+            // The pending long-polling request, replaced when a signal is first read
+            pending_poll: Arc<Mutex<Option<web_sys::XmlHttpRequest>>>,
+            // Set while the pending long-polling request is about to be replaced,
+            // so signals first read together replace it once
+            poll_restart_scheduled: Arc<Mutex<bool>>,
+            // Owns the state signals, which outlive the components reading them
+            owner: Owner,
+        }
+
+        /// The json state of a server signal, shared by every reader of the signal.
+        #[derive(Clone, Copy, Debug)]
+        struct StateSignal {
+            signal: RwSignal<Value>,
+            readers: usize,
         }
 
         impl ServerSignalWebSocket {
+            fn new(connection: Connection) -> Self {
+                ServerSignalWebSocket {
+                    connection: Arc::new(Mutex::new(connection)),
+                    state_signals: Default::default(),
+                    delayed_updates: Default::default(),
+                    delayed_stats: RwSignal::new(DelayedUpdateStats::default()),
+                    versions: Default::default(),
+                    status: RwSignal::new(ConnectionStatus::Connecting),
+                    error: RwSignal::new(None),
+                    reconnect: Default::default(),
+                    codec: Arc::new(Mutex::new(Arc::new(JsonCodec))),
+                    long_poll_fallback: Default::default(),
+                    pending_poll: Default::default(),
+                    poll_restart_scheduled: Default::default(),
+                    owner: Owner::current().unwrap_or_default(),
+                }
+            }
+
             /// Returns the inner websocket.
+            ///
+            /// # Panics
+            ///
+            /// Panics if connected through server-sent events or long-polling.
+            /// Use [`try_ws`](Self::try_ws) if the connection may not be a websocket.
             pub fn ws(&self) -> WebSocket {
-                self.ws.clone()
+                self.try_ws().expect("not connected through a websocket")
+            }
+
+            /// Returns the inner websocket, or `None` if connected through server-sent events or long-polling.
+            pub fn try_ws(&self) -> Option<WebSocket> {
+                match &*self.connection.lock().unwrap() {
+                    Connection::WebSocket(ws) => Some(ws.clone()),
+                    _ => None,
+                }
+            }
+
+            /// Returns the inner event source, or `None` if not connected through server-sent events.
+            pub fn event_source(&self) -> Option<EventSource> {
+                match &*self.connection.lock().unwrap() {
+                    Connection::EventSource(event_source) => Some(event_source.clone()),
+                    _ => None,
+                }
+            }
+
+            /// Returns the status of the websocket connection.
+            pub fn status(&self) -> ReadSignal<ConnectionStatus> {
+                self.status.read_only()
+            }
+
+            /// Returns the last error which occurred while handling messages from the server.
+            pub fn error(&self) -> ReadSignal<Option<ClientError>> {
+                self.error.read_only()
+            }
+
+            fn report_error(&self, error: ClientError) {
+                use leptos::prelude::Set;
+
+                leptos::logging::error!("{}", error);
+                self.error.set(Some(error));
+            }
+
+            /// Handles a frame received from the websocket.
+            fn handle_frame(&self, data: JsValue) {
+                use wasm_bindgen::JsCast;
+
+                let frame = if let Some(text) = data.as_string() {
+                    Frame::Text(text)
+                } else if let Some(buffer) = data.dyn_ref::<js_sys::ArrayBuffer>() {
+                    Frame::Binary(js_sys::Uint8Array::new(buffer).to_vec())
+                } else {
+                    self.report_error(ClientError::BadFrame {
+                        reason: "expected a text or binary frame".to_string(),
+                    });
+                    return;
+                };
+                #[cfg(feature = "compression")]
+                let frame = match decompress(frame) {
+                    Ok(frame) => frame,
+                    Err(err) => {
+                        self.report_error(ClientError::BadFrame { reason: err.to_string() });
+                        return;
+                    }
+                };
+                let codec = self.codec.lock().unwrap().clone();
+                match codec.decode_server(&frame) {
+                    Ok(message) => self.handle_message(message),
+                    Err(err) => self.report_error(ClientError::BadFrame {
+                        reason: err.to_string(),
+                    }),
+                }
+            }
+
+            fn handle_message(&self, message: ServerSignalMessage) {
+                use leptos::prelude::batch;
+
+                if let ServerSignalMessage::Batch(messages) = message {
+                    // The messages are applied synchronously, so effects scheduled by them run
+                    // after the whole batch. `batch` only defers immediate effects until then.
+                    batch(|| {
+                        for message in messages.into_messages() {
+                            self.handle_message(message);
+                        }
+                    });
+                    return;
+                }
+                let Some(name) = message.name() else {
+                    return;
+                };
+                let name: Cow<'static, str> = name.to_string().into();
+
+                // The lock is released before resyncing, which locks the versions again
+                let check = self.versions.lock().unwrap().check(&message);
+                match check {
+                    VersionCheck::Apply => {}
+                    VersionCheck::Ignore => return,
+                    VersionCheck::Resync => {
+                        leptos::logging::warn!("Missed update to {}. Requesting resync.", name);
+                        self.send_resync(name);
+                        return;
+                    }
+                }
+
+                let signal = self.state_signals.lock().unwrap().get(&name).map(|state| state.signal);
+                if let Some(signal) = signal {
+                    self.apply_messages(&name, signal, vec![message]);
+                } else {
+                    leptos::logging::warn!("No local state for update to {}. Queuing message.", name);
+                    let discarded = self.delayed_updates.lock().unwrap().push(name, message, js_sys::Date::now());
+                    self.discard_delayed(discarded);
+                }
+            }
+
+            /// Unsubscribes from signals whose queued messages were discarded, forgetting their versions,
+            /// so updates to them are ignored, and a resync is requested if they are read later.
+            fn discard_delayed(&self, names: Vec<Cow<'static, str>>) {
+                for name in names {
+                    leptos::logging::warn!("Discarded queued messages for {}, which has not been read.", name);
+                    let mut versions = self.versions.lock().unwrap();
+                    versions.versions.remove(&name);
+                    versions.resyncing.remove(&name);
+                    versions.unsubscribed.insert(name.clone());
+                    drop(versions);
+                    self.send_message(&ClientSignalMessage::Unsubscribe { name });
+                }
+                self.update_delayed_stats();
+            }
+
+            fn update_delayed_stats(&self) {
+                use leptos::prelude::Set;
+
+                let stats = self.delayed_updates.lock().unwrap().stats().clone();
+                self.delayed_stats.set(stats);
+            }
+
+            /// Applies messages to the json state of a signal in order.
+            ///
+            /// If a patch fails, the remaining messages are discarded, the signal keeps its last good value,
+            /// and a resync is requested.
+            fn apply_messages(&self, name: &str, signal: RwSignal<Value>, messages: Vec<ServerSignalMessage>) {
+                use leptos::prelude::Update;
+
+                let result = signal.try_maybe_update(|doc| {
+                    let mut changed = false;
+                    for message in messages {
+                        // Patches are applied atomically, leaving the document unchanged on failure
+                        if let Err(err) = message.apply(doc) {
+                            return (changed, Err(err));
+                        }
+                        changed = true;
+                    }
+                    (changed, Ok(()))
+                });
+                if let Some(Err(err)) = result {
+                    self.report_error(ClientError::PatchFailed {
+                        name: name.to_string().into(),
+                        reason: err.to_string(),
+                    });
+                    self.send_resync(name.to_string().into());
+                }
+            }
+
+            /// Deserializes the json state of a signal, reporting an error if it does not match `T`.
+            fn deserialize_value<T>(&self, name: &str, value: Value) -> Option<T>
+            where
+                T: for<'de> Deserialize<'de>,
+            {
+                match serde_json::from_value(value) {
+                    Ok(value) => Some(value),
+                    Err(err) => {
+                        self.report_error(ClientError::DeserializeFailed {
+                            name: name.to_string().into(),
+                            reason: err.to_string(),
+                        });
+                        None
+                    }
+                }
+            }
+
+            /// Sends the diff between the signal's server value and a value written by the client,
+            /// returning `true` if it was not empty and was sent.
+            ///
+            /// Updates which cannot be sent are reported as [`ClientError::UpdateNotSent`].
+            fn send_update(&self, name: Cow<'static, str>, server_json: &Value, new_json: &Value) -> bool {
+                let patch = json_patch::diff(server_json, new_json);
+                if patch.0.is_empty() {
+                    return false;
+                }
+                // The version the update was written against, which the server checks is current
+                let version = self.versions.lock().unwrap().versions.get(&name).copied().unwrap_or_default();
+                let update = ServerSignalUpdate { name: name.clone(), version, patch };
+                if !self.send_message(&ClientSignalMessage::Update(update)) {
+                    self.report_error(ClientError::UpdateNotSent { name });
+                    return false;
+                }
+                true
+            }
+
+            /// Returns `true` if a snapshot of a signal has been received, or provided during SSR.
+            fn is_synced(&self, name: &str) -> bool {
+                self.versions.lock().unwrap().versions.contains_key(name)
+            }
+
+            /// Requests a snapshot of a signal, ignoring updates to it until the snapshot is received.
+            fn send_resync(&self, name: Cow<'static, str>) {
+                self.versions.lock().unwrap().resyncing.insert(name.clone());
+                self.send_message(&ClientSignalMessage::Resync { name });
+            }
+
+            /// Creates the json state of a signal read for the first time,
+            /// applying any messages which arrived before it was created.
+            fn create_state_signal(&self, name: Cow<'static, str>, snapshot: Option<ServerSignalSnapshot>, default: Value) -> RwSignal<Value> {
+                let value = match snapshot {
+                    Some(snapshot) => {
+                        // Messages received before hydration may already be newer than the snapshot
+                        self.versions.lock().unwrap().versions.entry(name.clone()).or_insert(snapshot.version);
+                        snapshot.value
+                    }
+                    None => default,
+                };
+                let signal = self.owner.with(|| RwSignal::new(value));
+                self.state_signals.lock().unwrap().insert(name.clone(), StateSignal { signal, readers: 1 });
+
+                // Apply any messages which arrived before this signal was created,
+                // such as the initial snapshot sent when the connection was established.
+                let expired = self.delayed_updates.lock().unwrap().expire(js_sys::Date::now());
+                self.discard_delayed(expired);
+                let delayed_messages = self.delayed_updates.lock().unwrap().take(&name);
+                self.update_delayed_stats();
+                let mut resync = false;
+                match delayed_messages {
+                    Some(messages) if matches!(messages.first(), Some(ServerSignalMessage::Snapshot(_))) => {
+                        self.apply_messages(&name, signal, messages);
+                    }
+                    // Updates cannot be applied without the snapshot they follow
+                    Some(_) => resync = true,
+                    None => {}
+                }
+
+                // Signals read again after being unsubscribed or discarded have missed updates
+                if self.versions.lock().unwrap().unsubscribed.remove(&name) || resync {
+                    self.send_resync(name.clone());
+                } else {
+                    self.send_subscribe(name.clone());
+                }
+                signal
+            }
+
+            /// Removes a reader of a signal, unsubscribing from it once it has no readers left.
+            ///
+            /// The state of an unsubscribed signal is disposed, and it is resynced if it is read again.
+            fn unregister(&self, name: Cow<'static, str>) {
+                use leptos::prelude::Dispose;
+
+                let mut state_signals = self.state_signals.lock().unwrap();
+                let Some(state) = state_signals.get_mut(&name) else {
+                    return;
+                };
+                state.readers -= 1;
+                if state.readers > 0 {
+                    return;
+                }
+                let signal = state.signal;
+                state_signals.remove(&name);
+                drop(state_signals);
+
+                signal.dispose();
+                self.delayed_updates.lock().unwrap().take(&name);
+                self.update_delayed_stats();
+                let mut versions = self.versions.lock().unwrap();
+                versions.versions.remove(&name);
+                versions.resyncing.remove(&name);
+                versions.unsubscribed.insert(name.clone());
+                drop(versions);
+                self.send_message(&ClientSignalMessage::Unsubscribe { name });
+            }
+
+            /// Encodes a message with the negotiated codec and sends it to the server.
+            ///
+            /// Over server-sent events, resyncs reopen the connection, and over long-polling,
+            /// the next request asks for a snapshot or includes the subscribed signal.
+            /// Updates cannot be sent without a websocket.
+            /// Sends a message to the server, returning `false` if it could not be sent.
+            ///
+            /// Without a websocket, resyncs and subscriptions are handled by the connection instead,
+            /// while updates cannot be sent.
+            fn send_message(&self, message: &ClientSignalMessage) -> bool {
+                let connection = self.connection.lock().unwrap().clone();
+                let ws = match (connection, message) {
+                    (Connection::WebSocket(ws), _) => ws,
+                    (Connection::EventSource(_), ClientSignalMessage::Resync { .. }) => {
+                        self.reopen_event_source();
+                        return true;
+                    }
+                    (Connection::LongPoll(_), ClientSignalMessage::Resync { .. } | ClientSignalMessage::Subscribe { .. }) => {
+                        self.restart_long_poll();
+                        return true;
+                    }
+                    // Server-sent events send every signal, and long-polling requests list the signals which are read
+                    (_, ClientSignalMessage::Subscribe { .. } | ClientSignalMessage::Unsubscribe { .. }) => return true,
+                    (_, ClientSignalMessage::Update(_)) => return false,
+                };
+                let codec = self.codec.lock().unwrap().clone();
+                let result = match codec.encode_client(message) {
+                    Ok(Frame::Text(text)) => ws.send_with_str(&text),
+                    Ok(Frame::Binary(bytes)) => ws.send_with_u8_array(&bytes),
+                    Err(err) => {
+                        leptos::logging::error!("Failed to encode message to server: {}", err);
+                        return false;
+                    }
+                };
+                if let Err(err) = result {
+                    leptos::logging::error!("Failed to send message to server: {:?}", err);
+                    return false;
+                }
+                true
+            }
+
+            /// Subscribes to a signal which is read for the first time.
+            ///
+            /// Over a websocket which is not open yet, signals are subscribed to once it opens.
+            fn send_subscribe(&self, name: Cow<'static, str>) {
+                use leptos::prelude::GetUntracked;
+
+                let websocket = matches!(*self.connection.lock().unwrap(), Connection::WebSocket(_));
+                if !websocket || self.status.get_untracked().is_open() {
+                    self.send_message(&ClientSignalMessage::Subscribe { name });
+                }
+            }
+
+            /// Subscribes to every server signal, after the websocket has opened.
+            ///
+            /// After reconnecting, the versions received through the previous connection are forgotten,
+            /// so updates are only applied once the new connection has sent a snapshot,
+            /// and a resync is requested otherwise.
+            fn subscribe_all(&self, reconnected: bool) {
+                let names: Vec<_> = self.state_signals.lock().unwrap().keys().cloned().collect();
+                if reconnected {
+                    let mut versions = self.versions.lock().unwrap();
+                    for name in &names {
+                        versions.versions.remove(name);
+                        versions.resyncing.remove(name);
+                    }
+                }
+                for name in names {
+                    self.send_message(&ClientSignalMessage::Subscribe { name });
+                }
+            }
+
+            /// Switches to long-polling after the websocket failed to open.
+            fn start_long_poll(&self, url: String) {
+                use leptos::prelude::Set;
+
+                leptos::logging::warn!("Signal web-socket failed to open. Falling back to long-polling.");
+                *self.connection.lock().unwrap() = Connection::LongPoll(url);
+                self.status.set(ConnectionStatus::Connecting);
+                self.long_poll();
+            }
+
+            /// Requests the messages missed since the last received versions, polling again once they arrive.
+            ///
+            /// Failed requests are retried according to the reconnect policy.
+            fn long_poll(&self) {
+                use leptos::prelude::{GetUntracked, Set};
+                use wasm_bindgen::{prelude::Closure, JsCast};
+                use web_sys::XmlHttpRequest;
+
+                let Connection::LongPoll(url) = self.connection.lock().unwrap().clone() else {
+                    return;
+                };
+                let names: Vec<_> = self.state_signals.lock().unwrap().keys().cloned().collect();
+                let request = self.versions.lock().unwrap().long_poll_request().with_subscriptions(names);
+                let xhr = match XmlHttpRequest::new() {
+                    Ok(xhr) => xhr,
+                    Err(err) => {
+                        leptos::logging::error!("Failed to create long-polling request: {:?}", err);
+                        return;
+                    }
+                };
+
+                let server_signal_ws = self.clone();
+                let request_xhr = xhr.clone();
+                let on_load_end_callback = Closure::once_into_js(move || {
+                    server_signal_ws.pending_poll.lock().unwrap().take();
+                    match request_xhr.status() {
+                        Ok(200) => {
+                            server_signal_ws.status.set(ConnectionStatus::Open);
+                            if let Ok(Some(text)) = request_xhr.response_text() {
+                                server_signal_ws.handle_frame(JsValue::from_str(&text));
+                            }
+                            server_signal_ws.long_poll();
+                        }
+                        status => {
+                            let policy = server_signal_ws.reconnect.lock().unwrap().clone().unwrap_or_default();
+                            let attempt = match server_signal_ws.status.get_untracked() {
+                                ConnectionStatus::Reconnecting { attempt, .. } => attempt + 1,
+                                _ => 1,
+                            };
+                            let last_error = match status {
+                                Ok(0) | Err(_) => "long-polling request failed".to_string(),
+                                Ok(status) => format!("long-polling request failed with status {status}"),
+                            };
+                            server_signal_ws.schedule_reconnect(policy, attempt, last_error);
+                        }
+                    }
+                });
+                xhr.set_onloadend(Some(on_load_end_callback.unchecked_ref()));
+
+                let result = serde_json::to_string(&request).map_err(|err| JsValue::from_str(&err.to_string())).and_then(|body| {
+                    xhr.open("POST", &url)?;
+                    xhr.set_request_header("Content-Type", "application/json")?;
+                    xhr.send_with_opt_str(Some(&body))
+                });
+                match result {
+                    Ok(()) => *self.pending_poll.lock().unwrap() = Some(xhr),
+                    Err(err) => leptos::logging::error!("Failed to send long-polling request: {:?}", err),
+                }
+            }
+
+            /// Replaces the pending long-polling request with a new one, so it includes newly read signals.
+            ///
+            /// The request is replaced after the current task, once for every signal read meanwhile.
+            /// If no request is pending, such as while waiting to retry, the next request includes them.
+            fn restart_long_poll(&self) {
+                use wasm_bindgen::{prelude::Closure, JsCast};
+
+                if self.pending_poll.lock().unwrap().is_none() {
+                    return;
+                }
+                if std::mem::replace(&mut *self.poll_restart_scheduled.lock().unwrap(), true) {
+                    return;
+                }
+
+                let server_signal_ws = self.clone();
+                let on_timeout_callback = Closure::once_into_js(move || {
+                    *server_signal_ws.poll_restart_scheduled.lock().unwrap() = false;
+                    let pending = server_signal_ws.pending_poll.lock().unwrap().take();
+                    if let Some(xhr) = pending {
+                        xhr.set_onloadend(None);
+                        let _ = xhr.abort();
+                        server_signal_ws.long_poll();
+                    }
+                });
+                let scheduled = web_sys::window().and_then(|window| {
+                    window
+                        .set_timeout_with_callback_and_timeout_and_arguments_0(on_timeout_callback.unchecked_ref(), 0)
+                        .ok()
+                });
+                if scheduled.is_none() {
+                    leptos::logging::error!("Failed to schedule long-polling request.");
+                    *self.poll_restart_scheduled.lock().unwrap() = false;
+                }
+            }
+
+            /// Reopens the server-sent events connection, so that the server sends a snapshot of every signal.
+            ///
+            /// Nothing is done if the connection is already being opened.
+            fn reopen_event_source(&self) {
+                let mut connection = self.connection.lock().unwrap();
+                let Connection::EventSource(event_source) = &*connection else {
+                    return;
+                };
+                if event_source.ready_state() == EventSource::CONNECTING {
+                    return;
+                }
+                match EventSource::new(&event_source.url()) {
+                    Ok(new_event_source) => {
+                        event_source.close();
+                        new_event_source.set_onopen(event_source.onopen().as_ref());
+                        new_event_source.set_onmessage(event_source.onmessage().as_ref());
+                        new_event_source.set_onerror(event_source.onerror().as_ref());
+                        *connection = Connection::EventSource(new_event_source);
+                    }
+                    Err(err) => leptos::logging::error!("Failed to reopen server-sent events: {:?}", err),
+                }
+            }
+
+            /// Schedules a reconnect attempt according to the policy,
+            /// or gives up if the maximum number of attempts has been exceeded.
+            fn schedule_reconnect(&self, policy: ReconnectPolicy, attempt: u32, last_error: String) {
+                use leptos::prelude::Set;
+                use wasm_bindgen::{prelude::Closure, JsCast};
+
+                let Some(delay) = policy.delay(attempt, js_sys::Math::random()) else {
+                    leptos::logging::warn!(
+                        "Could not reconnect signal web-socket after {} attempts. Giving up.",
+                        attempt - 1
+                    );
+                    self.status.set(ConnectionStatus::Closed);
+                    policy.give_up();
+                    return;
+                };
+
+                self.status.set(ConnectionStatus::Reconnecting {
+                    attempt,
+                    last_error: Some(last_error),
+                });
+                leptos::logging::log!(
+                    "Connection lost to signal web-socket. Try to reconnect in {} milliseconds.",
+                    delay.as_millis()
+                );
+
+                let server_signal_ws = self.clone();
+                let on_timeout_callback = Closure::once_into_js(move || {
+                    server_signal_ws.reconnect(policy, attempt);
+                });
+                let scheduled = web_sys::window().and_then(|window| {
+                    window
+                        .set_timeout_with_callback_and_timeout_and_arguments_0(
+                            on_timeout_callback.unchecked_ref(),
+                            delay.as_millis().min(i32::MAX as u128) as i32,
+                        )
+                        .ok()
+                });
+                if scheduled.is_none() {
+                    leptos::logging::error!("Failed to schedule signal web-socket reconnect.");
+                    self.status.set(ConnectionStatus::Closed);
+                }
+            }
+
+            /// Replaces the websocket with a new connection to the same url,
+            /// keeping the event handlers of the previous one.
+            fn reconnect(&self, policy: ReconnectPolicy, attempt: u32) {
+                leptos::logging::log!("Try to reconnect signal web-socket.");
+                let mut connection = self.connection.lock().unwrap();
+                let ws = match &*connection {
+                    Connection::WebSocket(ws) => ws,
+                    Connection::LongPoll(_) => {
+                        drop(connection);
+                        self.long_poll();
+                        return;
+                    }
+                    Connection::EventSource(_) => return,
+                };
+                match open_websocket(&ws.url()) {
+                    Ok(new_ws) => {
+                        new_ws.set_onopen(ws.onopen().as_ref());
+                        new_ws.set_onmessage(ws.onmessage().as_ref());
+                        new_ws.set_onclose(ws.onclose().as_ref());
+                        new_ws.set_onerror(ws.onerror().as_ref());
+                        *connection = Connection::WebSocket(new_ws);
+                    }
+                    Err(err) => {
+                        drop(connection);
+                        self.schedule_reconnect(policy, attempt + 1, format!("{err:?}"));
+                    }
+                }
+            }
+        }
+
+        /// Updates the signal of each row from a new list of keyed values, returning the signals by key.
+        ///
+        /// Only rows whose value changed are updated, and the list of rows is only set when
+        /// rows are inserted, removed or reordered. Signals of removed rows are disposed.
+        /// Only the first value of a duplicate key is kept, so each key has a single row.
+        ///
+        /// New rows are created in `owner` rather than the calling effect,
+        /// so they are not disposed when the effect runs again.
+        fn sync_rows<K, T>(
+            owner: &Owner,
+            mut rows: HashMap<K, RwSignal<T>>,
+            values: impl IntoIterator<Item = (K, T)>,
+            set: WriteSignal<Vec<(K, ReadSignal<T>)>>,
+        ) -> HashMap<K, RwSignal<T>>
+        where
+            K: Clone + Eq + std::hash::Hash + Send + Sync + 'static,
+            T: PartialEq + Send + Sync + 'static,
+        {
+            use leptos::prelude::{Dispose, Set, Update, WithUntracked};
+
+            let mut next = HashMap::with_capacity(rows.len());
+            let mut list = Vec::with_capacity(rows.len());
+            for (key, value) in values {
+                if next.contains_key(&key) {
+                    continue;
+                }
+                let row = match rows.remove(&key) {
+                    Some(row) => {
+                        if row.with_untracked(|current| *current != value) {
+                            row.set(value);
+                        }
+                        row
+                    }
+                    None => owner.with(|| RwSignal::new(value)),
+                };
+                list.push((key.clone(), row.read_only()));
+                next.insert(key, row);
             }
+            for row in rows.into_values() {
+                row.dispose();
+            }
+
+            set.update(|current| {
+                let reordered = current.len() != list.len()
+                    || current.iter().zip(&list).any(|((current, _), (key, _))| current != key);
+                if reordered {
+                    *current = list;
+                }
+            });
+            next
+        }
+
+        /// Registers the json state of a server signal with the provided websocket,
+        /// applying any messages which arrived before it was created.
+        ///
+        /// The state starts from the snapshot provided during SSR if there is one, otherwise `T::default()`.
+        fn register_server_signal<T>(
+            name: Cow<'static, str>,
+            snapshot: Option<ServerSignalSnapshot>,
+        ) -> Option<(ServerSignalWebSocket, RwSignal<Value>)>
+        where
+            T: Default + Serialize,
+        {
+            register_server_json(name, snapshot, serde_json::to_value(T::default()).unwrap_or_default())
+        }
+
+        /// Registers a reader of the json state of a server signal, starting from `default` if there is no snapshot.
+        ///
+        /// Readers of the same signal share its state, and each is removed when its owner is disposed.
+        fn register_server_json(
+            name: Cow<'static, str>,
+            snapshot: Option<ServerSignalSnapshot>,
+            default: Value,
+        ) -> Option<(ServerSignalWebSocket, RwSignal<Value>)> {
+            use leptos::prelude::{on_cleanup, use_context};
+
+            let Some(ws) = use_context::<ServerSignalWebSocket>() else {
+                leptos::logging::error!(
+                    r#"server signal was used without a websocket being provided.
+
+Ensure you call `leptos_server_signal::provide_websocket("ws://localhost:3000/ws")` at the highest level in your app."#
+                );
+                return None;
+            };
+
+            // Readers of the same signal share its state
+            let shared = ws.state_signals.lock().unwrap().get_mut(&name).map(|state| {
+                state.readers += 1;
+                state.signal
+            });
+            let signal = match shared {
+                Some(signal) => signal,
+                None => ws.create_state_signal(name.clone(), snapshot, default),
+            };
+
+            let cleanup_ws = ws.clone();
+            on_cleanup(move || cleanup_ws.unregister(name));
+
+            Some((ws, signal))
+        }
+
+        /// Opens a websocket, offering the protocols of the enabled codecs.
+        ///
+        /// No protocols are offered if only json is enabled, as browsers close the connection
+        /// when a server ignores the offered protocols.
+        fn open_websocket(url: &str) -> Result<WebSocket, JsValue> {
+            let ws = if PROTOCOLS.len() > 1 {
+                let protocols: js_sys::Array = PROTOCOLS.iter().copied().map(JsValue::from_str).collect();
+                WebSocket::new_with_str_sequence(url, &protocols)?
+            } else {
+                WebSocket::new(url)?
+            };
+            ws.set_binary_type(web_sys::BinaryType::Arraybuffer);
+            Ok(ws)
         }
 
         #[inline]
         fn provide_websocket_inner(url: &str) -> Result<Option<WebSocket>, JsValue> {
-            use web_sys::MessageEvent;
+            use web_sys::{CloseEvent, Event, MessageEvent};
             use wasm_bindgen::{prelude::Closure, JsCast};
-            use leptos::prelude::{use_context, Update};
-            use js_sys::{Function, JsString};
+            use leptos::prelude::{use_context, GetUntracked, Set, Update};
+            use js_sys::Function;
 
             if use_context::<ServerSignalWebSocket>().is_none() {
-                let ws = WebSocket::new(url)?;
-                provide_context(ServerSignalWebSocket { ws, state_signals: Default::default(), delayed_updates: Default::default() });
-            }
-
-            let ws = use_context::<ServerSignalWebSocket>().unwrap();
+                let ws = open_websocket(url)?;
+                let server_signal_ws = ServerSignalWebSocket::new(Connection::WebSocket(ws.clone()));
 
-            let handlers = ws.state_signals.clone();
-            let delayed_updates = ws.delayed_updates.clone();
+                let on_open_ws = server_signal_ws.clone();
+                let on_open_callback = Closure::wrap(Box::new(move |_: Event| {
+                    let reconnected = matches!(on_open_ws.status.get_untracked(), ConnectionStatus::Reconnecting { .. });
+                    if let Some(ws) = on_open_ws.try_ws() {
+                        *on_open_ws.codec.lock().unwrap() = negotiate_codec(Some(&ws.protocol()));
+                    }
+                    on_open_ws.long_poll_fallback.lock().unwrap().take();
+                    on_open_ws.status.set(ConnectionStatus::Open);
+                    on_open_ws.subscribe_all(reconnected);
+                }) as Box<dyn FnMut(_)>);
+                ws.set_onopen(Some(on_open_callback.as_ref().unchecked_ref()));
+                on_open_callback.forget();
 
-            let callback = Closure::wrap(Box::new(move |event: MessageEvent| {
-                let ws_string = event.data().dyn_into::<JsString>().unwrap().as_string().unwrap();
-                if let Ok(update_signal) = serde_json::from_str::<ServerSignalUpdate>(&ws_string) {
-                    let handler_map = handlers.lock().unwrap();
-                    let name = &update_signal.name;
-                    let mut delayed_map = delayed_updates.lock().unwrap();
-                    if let Some(signal) = handler_map.get(name) {
-                        if let Some(delayed_patches) = delayed_map.remove(name) {
-                            signal.update(|doc| {
-                                for patch in delayed_patches {
-                                    json_patch::patch(doc, &patch).unwrap();
-                                }
-                            });
+                let status = server_signal_ws.status;
+                let on_error_callback = Closure::wrap(Box::new(move |_: Event| {
+                    status.update(|status| {
+                        if matches!(status, ConnectionStatus::Connecting | ConnectionStatus::Open) {
+                            *status = ConnectionStatus::Closing;
                         }
-                        signal.update(|doc| {
-                            json_patch::patch(doc, &update_signal.patch).unwrap();
-                        });
-                    } else {
-                        leptos::logging::warn!("No local state for update to {}. Queuing patch.", name);
-                        delayed_map.entry(name.clone()).or_default().push(update_signal.patch.clone());
+                    });
+                }) as Box<dyn FnMut(_)>);
+                ws.set_onerror(Some(on_error_callback.as_ref().unchecked_ref()));
+                on_error_callback.forget();
+
+                let on_close_ws = server_signal_ws.clone();
+                let on_close_callback = Closure::wrap(Box::new(move |event: CloseEvent| {
+                    let long_poll_url = on_close_ws.long_poll_fallback.lock().unwrap().take();
+                    if let Some(url) = long_poll_url {
+                        on_close_ws.start_long_poll(url);
+                        return;
                     }
-                }
+                    let policy = on_close_ws.reconnect.lock().unwrap().clone();
+                    let Some(policy) = policy else {
+                        on_close_ws.status.set(ConnectionStatus::Closed);
+                        return;
+                    };
+                    let attempt = match on_close_ws.status.get_untracked() {
+                        ConnectionStatus::Reconnecting { attempt, .. } => attempt + 1,
+                        _ => 1,
+                    };
+                    let last_error = if event.reason().is_empty() {
+                        format!("connection closed with code {}", event.code())
+                    } else {
+                        format!("connection closed with code {}: {}", event.code(), event.reason())
+                    };
+                    on_close_ws.schedule_reconnect(policy, attempt, last_error);
+                }) as Box<dyn FnMut(_)>);
+                ws.set_onclose(Some(on_close_callback.as_ref().unchecked_ref()));
+                on_close_callback.forget();
+
+                provide_context(server_signal_ws);
+            }
+
+            let server_signal_ws = use_context::<ServerSignalWebSocket>().unwrap();
+            let Some(ws) = server_signal_ws.try_ws() else {
+                return Err(JsValue::from_str("a server-sent events connection was already provided"));
+            };
+
+            let callback = Closure::wrap(Box::new(move |event: MessageEvent| {
+                server_signal_ws.handle_frame(event.data());
             }) as Box<dyn FnMut(_)>);
             let function: &Function = callback.as_ref().unchecked_ref();
-            ws.ws.set_onmessage(Some(function));
+            ws.set_onmessage(Some(function));
 
             // Keep the closure alive for the lifetime of the program
             callback.forget();
 
-            Ok(Some(ws.ws()))
+            Ok(Some(ws))
         }
 
         #[inline]
-        fn add_retry_timeout(ws: &WebSocket, timeout_in_ms: i32) {
-            use web_sys::{MessageEvent, window};
-            use wasm_bindgen::prelude::{Closure, JsCast};
-            use leptos::prelude::use_context;
-            use js_sys::Function;
+        fn provide_event_source_inner(url: &str) -> Result<Option<EventSource>, JsValue> {
+            use web_sys::{Event, MessageEvent};
+            use wasm_bindgen::{prelude::Closure, JsCast};
+            use leptos::prelude::{use_context, GetUntracked, Set};
+
+            if let Some(server_signal_ws) = use_context::<ServerSignalWebSocket>() {
+                return server_signal_ws
+                    .event_source()
+                    .map(Some)
+                    .ok_or_else(|| JsValue::from_str("a websocket connection was already provided"));
+            }
 
-            let mut server_signal_ws = use_context::<ServerSignalWebSocket>().unwrap();
+            let event_source = EventSource::new(url)?;
+            let server_signal_ws = ServerSignalWebSocket::new(Connection::EventSource(event_source.clone()));
 
-            let on_timeout_callback = Closure::wrap(Box::new(move |_: MessageEvent| {
-                leptos::logging::log!("Try to reconnect signal web-socket.");
-                let new_ws = WebSocket::new(server_signal_ws.ws.url().as_str()).unwrap();
-                new_ws.set_onmessage(server_signal_ws.ws.onmessage().as_ref());
-                new_ws.set_onclose(server_signal_ws.ws.onclose().as_ref());
-                new_ws.set_onerror(server_signal_ws.ws.onerror().as_ref());
-                server_signal_ws.ws = new_ws;
+            // Every connection starts with snapshots, so there is nothing to resync on open
+            let status = server_signal_ws.status;
+            let on_open_callback = Closure::wrap(Box::new(move |_: Event| {
+                status.set(ConnectionStatus::Open);
             }) as Box<dyn FnMut(_)>);
+            event_source.set_onopen(Some(on_open_callback.as_ref().unchecked_ref()));
+            on_open_callback.forget();
 
-            let on_error_callback = Closure::wrap(Box::new(move |_: MessageEvent| {
-                let on_timeout_function: &Function = on_timeout_callback.as_ref().unchecked_ref();
-                leptos::logging::log!(
-                    "Connection lost to signal web-socket. Try to reconnect in {} milliseconds.",
-                    timeout_in_ms
-                );
-                let _ = window().unwrap().set_timeout_with_callback_and_timeout_and_arguments_0(
-                    on_timeout_function,
-                    timeout_in_ms
-                );
+            // The browser reconnects automatically unless the connection was closed for good
+            let on_error_ws = server_signal_ws.clone();
+            let on_error_callback = Closure::wrap(Box::new(move |_: Event| {
+                let Some(event_source) = on_error_ws.event_source() else {
+                    return;
+                };
+                if event_source.ready_state() == EventSource::CLOSED {
+                    on_error_ws.status.set(ConnectionStatus::Closed);
+                    return;
+                }
+                let attempt = match on_error_ws.status.get_untracked() {
+                    ConnectionStatus::Reconnecting { attempt, .. } => attempt + 1,
+                    _ => 1,
+                };
+                on_error_ws.status.set(ConnectionStatus::Reconnecting { attempt, last_error: None });
             }) as Box<dyn FnMut(_)>);
-            let on_error_function: &Function = on_error_callback.as_ref().unchecked_ref();
-            ws.set_onerror(Some(on_error_function));
+            event_source.set_onerror(Some(on_error_callback.as_ref().unchecked_ref()));
             on_error_callback.forget();
+
+            let on_message_ws = server_signal_ws.clone();
+            let on_message_callback = Closure::wrap(Box::new(move |event: MessageEvent| {
+                on_message_ws.handle_frame(event.data());
+            }) as Box<dyn FnMut(_)>);
+            event_source.set_onmessage(Some(on_message_callback.as_ref().unchecked_ref()));
+            on_message_callback.forget();
+
+            provide_context(server_signal_ws);
+
+            Ok(Some(event_source))
+        }
+
+        #[inline]
+        fn provide_long_poll_inner(url: &str) -> Result<(), JsValue> {
+            use leptos::prelude::use_context;
+
+            if let Some(server_signal_ws) = use_context::<ServerSignalWebSocket>() {
+                return match &*server_signal_ws.connection.lock().unwrap() {
+                    Connection::LongPoll(_) => Ok(()),
+                    _ => Err(JsValue::from_str("a websocket or server-sent events connection was already provided")),
+                };
+            }
+
+            let server_signal_ws = ServerSignalWebSocket::new(Connection::LongPoll(url.to_string()));
+            provide_context(server_signal_ws.clone());
+            server_signal_ws.long_poll();
+
+            Ok(())
+        }
+
+        #[inline]
+        fn set_long_poll_fallback(url: &str) {
+            use leptos::prelude::use_context;
+
+            let server_signal_ws = use_context::<ServerSignalWebSocket>().unwrap();
+            *server_signal_ws.long_poll_fallback.lock().unwrap() = Some(url.to_string());
+        }
+
+        #[inline]
+        fn set_reconnect_policy(policy: ReconnectPolicy) {
+            use leptos::prelude::use_context;
+
+            let server_signal_ws = use_context::<ServerSignalWebSocket>().unwrap();
+            *server_signal_ws.reconnect.lock().unwrap() = Some(policy);
         }
     } else {
         #[inline]
@@ -303,6 +2395,19 @@ cfg_if::cfg_if! {
         }
 
         #[inline]
-        fn add_retry_timeout(_ws: &WebSocket, _timeout_in_ms: i32) {}
+        fn provide_event_source_inner(_url: &str) -> Result<Option<EventSource>, JsValue> {
+            Ok(None)
+        }
+
+        #[inline]
+        fn provide_long_poll_inner(_url: &str) -> Result<(), JsValue> {
+            Ok(())
+        }
+
+        #[inline]
+        fn set_long_poll_fallback(_url: &str) {}
+
+        #[inline]
+        fn set_reconnect_policy(_policy: ReconnectPolicy) {}
     }
 }
@@ -0,0 +1,150 @@
+use std::fmt;
+use std::sync::Arc;
+
+use futures::channel::mpsc::{self, UnboundedSender};
+use futures::stream::StreamExt;
+
+use crate::{LongPollRequest, ServerSignalBatch, ServerSignalHub, ServerSignalMessage};
+
+/// A signal which long-polling requests can wait on.
+pub(crate) trait PollSignal: Send + Sync {
+    /// Returns the messages missed since the version in the request.
+    ///
+    /// If there are none, and a `waiter` is given, it is registered to be notified of the next update.
+    fn poll(
+        &self,
+        request: &LongPollRequest,
+        waiter: Option<&UnboundedSender<()>>,
+    ) -> Vec<ServerSignalMessage>;
+}
+
+/// Answers long-polling requests for a set of [`ServerSignalHub`]s.
+///
+/// Long-polling is a fallback for networks where neither websockets nor server-sent events work.
+/// The client repeatedly requests the messages it has missed since the last versions it received,
+/// and the server holds each request until there is at least one message to send.
+///
+/// Each hub keeps a short history of updates to answer requests from. Clients which are too far behind,
+/// or have not received a signal yet, are sent a snapshot instead.
+///
+/// Only [`ServerSignalHub`]s can be polled. A [`ServerSignal`](crate::ServerSignal) belongs to a single
+/// connection and keeps no history, so it cannot answer requests which arrive without one.
+///
+/// Cloning is cheap, and clones share the same hubs.
+///
+/// # Example
+///
+/// ```ignore
+/// let long_poll = LongPoll::new().signal(&count).signal(&messages);
+///
+/// pub async fn poll(
+///     State(long_poll): State<LongPoll>,
+///     Json(request): Json<LongPollRequest>,
+/// ) -> Json<ServerSignalMessage> {
+///     let batch = tokio::time::timeout(Duration::from_secs(30), long_poll.poll(&request))
+///         .await
+///         .unwrap_or_default();
+///     Json(batch.into())
+/// }
+/// ```
+#[derive(Clone, Default)]
+pub struct LongPoll {
+    signals: Vec<Arc<dyn PollSignal>>,
+}
+
+impl LongPoll {
+    /// Creates a new [`LongPoll`] without any signals.
+    pub fn new() -> Self {
+        LongPoll::default()
+    }
+
+    /// Adds a hub whose messages are sent to long-polling clients.
+    pub fn signal<T>(mut self, hub: &ServerSignalHub<T>) -> Self
+    where
+        T: Send + 'static,
+    {
+        self.signals.push(Arc::new(hub.clone()));
+        self
+    }
+
+    /// Waits until there are messages the client has missed, returning them as a batch.
+    ///
+    /// This waits indefinitely, so should be wrapped in a timeout by the request handler,
+    /// responding with an empty batch if it elapses.
+    pub async fn poll(&self, request: &LongPollRequest) -> ServerSignalBatch {
+        let (waiter, mut updated) = mpsc::unbounded();
+        let mut batch = self.collect(request, Some(&waiter));
+        while batch.is_empty() {
+            if updated.next().await.is_none() {
+                break;
+            }
+            // The waiter is still registered with every signal which has not been updated
+            batch = self.collect(request, None);
+        }
+        batch
+    }
+
+    /// Returns the messages missed from every signal, registering `waiter` with signals which have none.
+    fn collect(
+        &self,
+        request: &LongPollRequest,
+        waiter: Option<&UnboundedSender<()>>,
+    ) -> ServerSignalBatch {
+        let mut batch = ServerSignalBatch::new();
+        for signal in &self.signals {
+            for message in signal.poll(request, waiter) {
+                batch.push(message);
+            }
+        }
+        batch
+    }
+}
+
+impl fmt::Debug for LongPoll {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LongPoll")
+            .field("signals", &self.signals.len())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::executor::block_on;
+    use futures::future;
+
+    use super::*;
+
+    #[test]
+    fn poll_waits_for_next_update() {
+        let count = ServerSignalHub::<i32>::new("count").unwrap();
+        let total = ServerSignalHub::<i32>::new("total").unwrap();
+        let long_poll = LongPoll::new().signal(&count).signal(&total);
+        let request = LongPollRequest::new()
+            .with_version("count", 0)
+            .with_version("total", 0);
+
+        // The update is made after the request starts waiting
+        let (batch, ()) = block_on(future::join(long_poll.poll(&request), async {
+            count.with(|count| *count = 1).unwrap();
+        }));
+        assert_eq!(batch.len(), 1);
+        assert_eq!(batch.messages()[0].name(), Some("count"));
+    }
+
+    #[test]
+    fn poll_registers_waiter_once() {
+        let count = ServerSignalHub::<i32>::new("count").unwrap();
+        let total = ServerSignalHub::<i32>::new("total").unwrap();
+        let long_poll = LongPoll::new().signal(&count).signal(&total);
+        let request = LongPollRequest::new()
+            .with_version("count", 0)
+            .with_version("total", 0);
+
+        block_on(future::join(long_poll.poll(&request), async {
+            count.with(|count| *count = 1).unwrap();
+        }));
+        // Polling again after the update does not register the waiter with `total` a second time
+        assert_eq!(total.waiter_count(), 1);
+    }
+}
@@ -0,0 +1,57 @@
+use futures::sink::SinkExt;
+use futures::stream::{SplitSink, Stream, StreamExt};
+use poem::http::header;
+use poem::web::websocket::{Message, WebSocketStream};
+use poem::{Body, IntoResponse, Response};
+
+use crate::{EventStream, Frame, Transport};
+
+impl Transport for WebSocketStream {
+    type Error = std::io::Error;
+
+    async fn send_frame(&mut self, frame: Frame) -> Result<(), Self::Error> {
+        self.send(frame.into()).await
+    }
+}
+
+impl Transport for SplitSink<WebSocketStream, Message> {
+    type Error = std::io::Error;
+
+    async fn send_frame(&mut self, frame: Frame) -> Result<(), Self::Error> {
+        self.send(frame.into()).await
+    }
+}
+
+impl From<Frame> for Message {
+    fn from(frame: Frame) -> Self {
+        match frame {
+            Frame::Text(text) => Message::Text(text),
+            Frame::Binary(bytes) => Message::Binary(bytes),
+        }
+    }
+}
+
+impl TryFrom<Message> for Frame {
+    type Error = Message;
+
+    /// Converts a text or binary message into a frame, returning control messages as an error.
+    fn try_from(message: Message) -> Result<Self, Self::Error> {
+        match message {
+            Message::Text(text) => Ok(Frame::Text(text)),
+            Message::Binary(bytes) => Ok(Frame::Binary(bytes)),
+            message => Err(message),
+        }
+    }
+}
+
+impl<S> IntoResponse for EventStream<S>
+where
+    S: Stream<Item = Frame> + Unpin + Send + 'static,
+{
+    fn into_response(self) -> Response {
+        Response::builder()
+            .content_type("text/event-stream")
+            .header(header::CACHE_CONTROL, "no-cache")
+            .body(Body::from_bytes_stream(self.map(Ok::<_, std::io::Error>)))
+    }
+}
@@ -0,0 +1,219 @@
+use std::fmt;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// A policy for reconnecting the websocket after the connection is lost.
+///
+/// The delay before each attempt starts at `initial_delay`, and is multiplied by `multiplier`
+/// after every failed attempt up to `max_delay`.
+/// Each delay is then randomly adjusted by up to `jitter` (as a fraction of the delay),
+/// so many clients losing their connection at once do not reconnect at the same time.
+///
+/// # Example
+///
+/// ```
+/// # use std::time::Duration;
+/// # use leptos_server_signal::ReconnectPolicy;
+/// let policy = ReconnectPolicy::new()
+///     .initial_delay(Duration::from_millis(500))
+///     .multiplier(2.0)
+///     .max_delay(Duration::from_secs(30))
+///     .jitter(0.2)
+///     .max_attempts(10)
+///     .on_give_up(|| leptos::logging::error!("Could not reconnect to the server."));
+/// ```
+#[derive(Clone)]
+pub struct ReconnectPolicy {
+    initial_delay: Duration,
+    multiplier: f64,
+    max_delay: Duration,
+    jitter: f64,
+    max_attempts: Option<u32>,
+    on_give_up: Option<Arc<dyn Fn() + Send + Sync>>,
+}
+
+impl ReconnectPolicy {
+    /// Creates a new [`ReconnectPolicy`] with exponential backoff.
+    ///
+    /// The defaults are an initial delay of 1 second, a multiplier of 2, a maximum delay of 30 seconds,
+    /// a jitter of 0.2, and unlimited attempts.
+    pub fn new() -> Self {
+        ReconnectPolicy {
+            initial_delay: Duration::from_secs(1),
+            multiplier: 2.0,
+            max_delay: Duration::from_secs(30),
+            jitter: 0.2,
+            max_attempts: None,
+            on_give_up: None,
+        }
+    }
+
+    /// Creates a new [`ReconnectPolicy`] which waits the same delay before every attempt.
+    pub fn fixed(delay: Duration) -> Self {
+        ReconnectPolicy::new()
+            .initial_delay(delay)
+            .multiplier(1.0)
+            .max_delay(delay)
+            .jitter(0.0)
+    }
+
+    /// Sets the delay before the first reconnect attempt.
+    pub fn initial_delay(mut self, delay: Duration) -> Self {
+        self.initial_delay = delay;
+        self
+    }
+
+    /// Sets the factor the delay is multiplied by after each failed attempt.
+    pub fn multiplier(mut self, multiplier: f64) -> Self {
+        self.multiplier = multiplier.max(1.0);
+        self
+    }
+
+    /// Sets the maximum delay between attempts.
+    pub fn max_delay(mut self, delay: Duration) -> Self {
+        self.max_delay = delay;
+        self
+    }
+
+    /// Sets the fraction of the delay which is randomly added or subtracted, between `0.0` and `1.0`.
+    pub fn jitter(mut self, jitter: f64) -> Self {
+        self.jitter = jitter.clamp(0.0, 1.0);
+        self
+    }
+
+    /// Sets the number of attempts after which reconnecting is given up.
+    pub fn max_attempts(mut self, max_attempts: u32) -> Self {
+        self.max_attempts = Some(max_attempts);
+        self
+    }
+
+    /// Sets a callback which is called when reconnecting is given up.
+    pub fn on_give_up(mut self, f: impl Fn() + Send + Sync + 'static) -> Self {
+        self.on_give_up = Some(Arc::new(f));
+        self
+    }
+
+    /// Returns the delay before the given attempt, starting at 1,
+    /// or `None` if the maximum number of attempts has been exceeded.
+    ///
+    /// `random` should be a random number between `0.0` and `1.0`, and is used to apply jitter.
+    pub fn delay(&self, attempt: u32, random: f64) -> Option<Duration> {
+        if self.max_attempts.is_some_and(|max_attempts| attempt > max_attempts) {
+            return None;
+        }
+        let exponent = attempt.saturating_sub(1).min(i32::MAX as u32) as i32;
+        let delay = (self.initial_delay.as_secs_f64() * self.multiplier.powi(exponent))
+            .min(self.max_delay.as_secs_f64());
+        let jitter = delay * self.jitter * (2.0 * random.clamp(0.0, 1.0) - 1.0);
+        // Delays beyond `Duration::MAX` saturate at the max delay
+        Some(Duration::try_from_secs_f64((delay + jitter).max(0.0)).unwrap_or(self.max_delay))
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    pub(crate) fn give_up(&self) {
+        if let Some(on_give_up) = &self.on_give_up {
+            on_give_up();
+        }
+    }
+}
+
+impl Default for ReconnectPolicy {
+    fn default() -> Self {
+        ReconnectPolicy::new()
+    }
+}
+
+impl fmt::Debug for ReconnectPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ReconnectPolicy")
+            .field("initial_delay", &self.initial_delay)
+            .field("multiplier", &self.multiplier)
+            .field("max_delay", &self.max_delay)
+            .field("jitter", &self.jitter)
+            .field("max_attempts", &self.max_attempts)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn secs(policy: &ReconnectPolicy, attempt: u32, random: f64) -> f64 {
+        policy.delay(attempt, random).unwrap().as_secs_f64()
+    }
+
+    fn assert_secs(actual: f64, expected: f64) {
+        assert!(
+            (actual - expected).abs() < 1e-9,
+            "expected {expected}s, got {actual}s"
+        );
+    }
+
+    #[test]
+    fn delay_grows_exponentially_up_to_max() {
+        let policy = ReconnectPolicy::new().jitter(0.0);
+        let delays: Vec<f64> = (1..=7).map(|attempt| secs(&policy, attempt, 0.5)).collect();
+        assert_eq!(delays, [1.0, 2.0, 4.0, 8.0, 16.0, 30.0, 30.0]);
+    }
+
+    #[test]
+    fn delay_is_capped_for_large_attempts() {
+        let policy = ReconnectPolicy::new().jitter(0.0);
+        assert_secs(secs(&policy, u32::MAX, 0.5), 30.0);
+        // Attempt 0 is treated as the first attempt
+        assert_secs(secs(&policy, 0, 0.5), 1.0);
+    }
+
+    #[test]
+    fn jitter_stays_within_bounds() {
+        let policy = ReconnectPolicy::new().jitter(0.2);
+        assert_secs(secs(&policy, 1, 0.0), 0.8);
+        assert_secs(secs(&policy, 1, 0.5), 1.0);
+        assert_secs(secs(&policy, 1, 1.0), 1.2);
+        // Jitter is applied after the delay is capped
+        assert_secs(secs(&policy, 10, 1.0), 36.0);
+
+        // Random numbers out of range are clamped
+        assert_secs(secs(&policy, 1, -1.0), 0.8);
+        assert_secs(secs(&policy, 1, 2.0), 1.2);
+        for random in [0.0, 0.1, 0.3, 0.7, 0.9, 1.0] {
+            let delay = secs(&policy, 3, random);
+            assert!((3.2..=4.8).contains(&delay), "{delay}s out of bounds");
+        }
+    }
+
+    #[test]
+    fn delay_is_none_after_max_attempts() {
+        let policy = ReconnectPolicy::new().max_attempts(3);
+        assert!(policy.delay(3, 0.5).is_some());
+        assert!(policy.delay(4, 0.5).is_none());
+        assert!(ReconnectPolicy::new().delay(u32::MAX, 0.5).is_some());
+    }
+
+    #[test]
+    fn delay_saturates_at_max_duration() {
+        let policy = ReconnectPolicy::new().max_delay(Duration::MAX).jitter(0.5);
+        assert_eq!(policy.delay(u32::MAX, 1.0), Some(Duration::MAX));
+        assert_eq!(policy.delay(u32::MAX, 0.5), Some(Duration::MAX));
+        assert!(policy.delay(u32::MAX, 0.0).unwrap() < Duration::MAX);
+    }
+
+    #[test]
+    fn fixed_delay_does_not_change() {
+        let policy = ReconnectPolicy::fixed(Duration::from_millis(250));
+        for (attempt, random) in [(1, 0.0), (2, 1.0), (100, 0.3)] {
+            assert_secs(secs(&policy, attempt, random), 0.25);
+        }
+    }
+
+    #[test]
+    fn builder_clamps_values() {
+        let policy = ReconnectPolicy::new().multiplier(0.5).jitter(0.0);
+        assert_secs(secs(&policy, 5, 0.5), 1.0);
+
+        let policy = ReconnectPolicy::new().jitter(5.0);
+        assert_secs(secs(&policy, 1, 0.0), 0.0);
+        assert_secs(secs(&policy, 1, 1.0), 2.0);
+    }
+}
@@ -0,0 +1,730 @@
+use std::borrow::Cow;
+use std::error::Error as StdError;
+use std::future::Future;
+use std::ops;
+use std::sync::Arc;
+use std::time::Duration;
+
+use futures::channel::mpsc::{SendError, UnboundedSender};
+use json_patch::Patch;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use thiserror::Error;
+
+use crate::state::SignalState;
+#[cfg(feature = "compression")]
+use crate::Compression;
+use crate::{
+    ClientSignalMessage, Codec, CodecError, Diff, DiffStrategy, Frame, JsonCodec,
+    ServerSignalBatch, ServerSignalMessage, ServerSignalSnapshot, ServerSignalUpdate, SignalKey,
+    Throttle, Validator,
+};
+
+/// A connection which server signals send frames through.
+///
+/// This is implemented for the websockets of the supported web frameworks when their feature is enabled,
+/// and for unbounded channel senders, and can be implemented to use server signals with other transports.
+///
+/// # Example
+///
+/// ```ignore
+/// struct MyTransport(tokio::sync::mpsc::Sender<Frame>);
+///
+/// impl Transport for MyTransport {
+///     type Error = tokio::sync::mpsc::error::SendError<Frame>;
+///
+///     async fn send_frame(&mut self, frame: Frame) -> Result<(), Self::Error> {
+///         self.0.send(frame).await
+///     }
+/// }
+/// ```
+pub trait Transport {
+    /// The error returned when a frame cannot be sent.
+    type Error: StdError + Send + Sync + 'static;
+
+    /// Sends a frame through the connection.
+    fn send_frame(&mut self, frame: Frame) -> impl Future<Output = Result<(), Self::Error>> + Send;
+}
+
+impl Transport for UnboundedSender<Frame> {
+    type Error = SendError;
+
+    async fn send_frame(&mut self, frame: Frame) -> Result<(), Self::Error> {
+        self.unbounded_send(frame)
+            .map_err(|err| err.into_send_error())
+    }
+}
+
+/// A signal owned by the server which writes to the websocket when mutated.
+///
+/// Frames are sent through the [`Transport`] passed to each method,
+/// such as an axum, poem or tokio-tungstenite websocket, or an actix `Session`.
+#[derive(Clone, Debug)]
+pub struct ServerSignal<T> {
+    state: SignalState<T>,
+    // Set when the client subscribes, and cleared when it unsubscribes
+    subscribed: bool,
+    codec: Arc<dyn Codec>,
+}
+
+impl<T> ServerSignal<T> {
+    /// Creates a new [`ServerSignal`], initializing `T` to default.
+    ///
+    /// This function can fail if serialization of `T` fails.
+    pub fn new(name: impl Into<Cow<'static, str>>) -> Result<Self, serde_json::Error>
+    where
+        T: Default + Serialize,
+    {
+        ServerSignal::new_with_value(name, T::default())
+    }
+
+    /// Creates a new [`ServerSignal`] with an initial value, for types which do not implement `Default`.
+    ///
+    /// This function can fail if serialization of `T` fails.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// let user = ServerSignal::new_with_value("user", User { name: "ari".to_string() }).unwrap();
+    /// ```
+    pub fn new_with_value(
+        name: impl Into<Cow<'static, str>>,
+        value: T,
+    ) -> Result<Self, serde_json::Error>
+    where
+        T: Serialize,
+    {
+        Ok(ServerSignal {
+            state: SignalState::new(name.into(), value)?,
+            subscribed: true,
+            codec: Arc::new(JsonCodec),
+        })
+    }
+
+    /// Creates a new [`ServerSignal`] named by a [`SignalKey`], initializing `T` to default.
+    ///
+    /// The key ensures the signal's name and type match the client's.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// signal_key! {
+    ///     pub Counter: Count = "counter";
+    /// }
+    ///
+    /// let count = ServerSignal::from_key(Counter).unwrap();
+    /// ```
+    pub fn from_key<K>(_key: K) -> Result<Self, serde_json::Error>
+    where
+        K: SignalKey<Value = T>,
+        T: Default + Serialize,
+    {
+        ServerSignal::new(K::NAME)
+    }
+
+    /// Allows the client to write to the signal, accepting updates for which `validate` returns `true`.
+    ///
+    /// Without this, updates written by the client are rejected.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// let count = ServerSignal::<Count>::new("counter")
+    ///     .unwrap()
+    ///     .writable(|count| count.value >= 0);
+    /// ```
+    pub fn writable(mut self, validate: impl Fn(&T) -> bool + Send + Sync + 'static) -> Self {
+        self.state.validator = Some(Validator::new(validate));
+        self
+    }
+
+    /// Limits how often changes are sent to at most once per `interval`.
+    ///
+    /// Mutations are applied locally immediately, while changes made within `interval` of the
+    /// last send are held back and coalesced into a single update.
+    /// Pending changes are sent by the next mutation after the interval has passed,
+    /// or by [`ServerSignal::flush`].
+    ///
+    /// Nothing sends pending changes on its own, so [`ServerSignal::flush`] must be called on a timer
+    /// ticking every `interval`. Otherwise the last change before a quiet period is held back
+    /// until the next mutation.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// let mut count = ServerSignal::<Count>::new("counter")
+    ///     .unwrap()
+    ///     .throttle(Duration::from_millis(100));
+    ///
+    /// let mut interval = tokio::time::interval(Duration::from_millis(100));
+    /// loop {
+    ///     tokio::select! {
+    ///         _ = interval.tick() => count.flush(&mut websocket).await?,
+    ///         event = events.recv() => {
+    ///             count.with(&mut websocket, |count| count.apply(event)).await?;
+    ///         }
+    ///     }
+    /// }
+    /// ```
+    pub fn throttle(mut self, interval: Duration) -> Self {
+        self.state.throttle = Throttle::new(interval);
+        self
+    }
+
+    /// Computes diffs with the value's [`Diff`] implementation, instead of serializing and diffing the whole value.
+    ///
+    /// A copy of the last sent value is kept to compare against, and only changed fields are serialized.
+    /// Snapshots are still sent in full.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// #[derive(Clone, Default, PartialEq, Serialize, Deserialize, Diff)]
+    /// pub struct State {
+    ///     pub count: i32,
+    ///     pub messages: Vec<String>,
+    /// }
+    ///
+    /// let state = ServerSignal::<State>::new("state").unwrap().diffed();
+    /// ```
+    pub fn diffed(mut self) -> Self
+    where
+        T: Diff + Clone,
+    {
+        self.state.track();
+        self
+    }
+
+    /// Sets the strategy used to compute diffs, which defaults to [`JsonDiff`].
+    ///
+    /// This is not used by signals with [`Diff`] implementations, which diff themselves.
+    pub fn diff_strategy(mut self, strategy: Arc<dyn DiffStrategy>) -> Self {
+        self.state.strategy = strategy;
+        self
+    }
+
+    /// Sends a snapshot instead of a diff when the json-encoded diff is larger than
+    /// `threshold` times the size of the full value.
+    ///
+    /// For example, with a threshold of `0.5`, a snapshot is sent whenever the diff is more than
+    /// half the size of the value. This does not apply to signals with [`Diff`] implementations.
+    pub fn snapshot_threshold(mut self, threshold: f64) -> Self {
+        self.state.snapshot_threshold = Some(threshold);
+        self
+    }
+
+    /// Sends nothing until the client subscribes to the signal, by reading it on the page.
+    ///
+    /// Without this, changes are sent whether or not the client reads the signal.
+    /// Subscribe requests must be passed to [`ServerSignal::handle_message`].
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// let count = ServerSignal::<Count>::new("counter").unwrap().lazy();
+    /// ```
+    pub fn lazy(mut self) -> Self {
+        self.subscribed = false;
+        self
+    }
+
+    /// Sets the codec used to encode messages sent through the websocket connection.
+    ///
+    /// This should be the codec negotiated for the connection with [`negotiate_codec`](crate::negotiate_codec).
+    pub fn codec(mut self, codec: Arc<dyn Codec>) -> Self {
+        self.codec = codec;
+        self
+    }
+
+    /// Compresses messages sent through the websocket connection which exceed the threshold size.
+    #[cfg(feature = "compression")]
+    pub fn compression(mut self, compression: Compression) -> Self {
+        self.state.compression = Some(compression);
+        self
+    }
+
+    /// Modifies the signal in a closure, and sends the json diffs through the websocket connection after modifying.
+    ///
+    /// If no snapshot has been sent yet, the full value is sent as a snapshot instead of a diff.
+    /// Nothing is sent if the value did not change.
+    /// If the signal is [throttled](ServerSignal::throttle), the diff may be held back until the next flush.
+    ///
+    /// The same websocket connection should be used for a given client, otherwise the signal could become out of sync.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// let count = ServerSignal::new("counter").unwrap();
+    /// count.with(&mut websocket, |count| {
+    ///     count.value += 1;
+    /// }).await?;
+    /// ```
+    pub async fn with<O, W>(
+        &mut self,
+        transport: &mut W,
+        f: impl FnOnce(&mut T) -> O,
+    ) -> Result<O, Error>
+    where
+        T: Clone + Serialize + 'static,
+        W: Transport,
+    {
+        let output = f(&mut self.state.value);
+        if self.subscribed && self.state.throttle.ready() {
+            self.send_value(transport).await?;
+        }
+        Ok(output)
+    }
+
+    /// Modifies the signal in a closure which returns the patch from the last sent json value,
+    /// sending it as the update instead of diffing the whole value.
+    ///
+    /// If no snapshot has been sent yet, or throttled changes are pending,
+    /// the whole value is diffed instead.
+    pub(crate) async fn with_patch<O, W>(
+        &mut self,
+        transport: &mut W,
+        f: impl FnOnce(&mut T) -> Result<(O, Patch), serde_json::Error>,
+    ) -> Result<O, Error>
+    where
+        T: Serialize,
+        W: Transport,
+    {
+        let pending = self.state.throttle.is_pending();
+        let (output, patch) = f(&mut self.state.value)?;
+        if !self.subscribed || (patch.0.is_empty() && !pending) {
+            return Ok(output);
+        }
+        if self.state.throttle.ready() {
+            if self.state.synced && !pending {
+                let message = ServerSignalUpdate::new_from_patch(self.state.name.clone(), patch)
+                    .with_version(self.state.version + 1)
+                    .into();
+                self.send(transport, &message).await?;
+                self.state.commit(&message, None)?;
+            } else {
+                self.send_value(transport).await?;
+            }
+        }
+        Ok(output)
+    }
+
+    /// Returns the strategy used to compute diffs.
+    pub(crate) fn strategy(&self) -> &Arc<dyn DiffStrategy> {
+        &self.state.strategy
+    }
+
+    /// Sends any changes held back by [throttling](ServerSignal::throttle) as a single update.
+    ///
+    /// Throttled signals must call this on a timer, see [`ServerSignal::throttle`].
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// let mut interval = tokio::time::interval(Duration::from_millis(100));
+    /// loop {
+    ///     interval.tick().await;
+    ///     count.flush(&mut websocket).await?;
+    /// }
+    /// ```
+    pub async fn flush<W>(&mut self, transport: &mut W) -> Result<(), Error>
+    where
+        T: Serialize,
+        W: Transport,
+    {
+        if self.subscribed && self.state.throttle.is_pending() {
+            self.send_value(transport).await?;
+        }
+        Ok(())
+    }
+
+    /// Sends the diff from the last sent json value to the current value.
+    async fn send_value<W>(&mut self, transport: &mut W) -> Result<(), Error>
+    where
+        T: Serialize,
+        W: Transport,
+    {
+        match self.state.next_message()? {
+            Some((message, new_json)) => {
+                self.send(transport, &message).await?;
+                self.state.commit(&message, new_json)?;
+            }
+            None => self.state.throttle.sent(),
+        }
+        Ok(())
+    }
+
+    /// Modifies the signal in a closure, and adds the json diff to a batch instead of sending it.
+    ///
+    /// Batches containing updates to several signals are sent with [`ServerSignal::send_batch`],
+    /// and applied together by the client.
+    /// If the batch is never sent, the client detects the missed update and requests a resync.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// let mut batch = ServerSignalBatch::new();
+    /// count.with_batched(&mut batch, |count| count.value += 1)?;
+    /// total.with_batched(&mut batch, |total| total.value += 1)?;
+    /// count.send_batch(&mut websocket, batch).await?;
+    /// ```
+    pub fn with_batched<O>(
+        &mut self,
+        batch: &mut ServerSignalBatch,
+        f: impl FnOnce(&mut T) -> O,
+    ) -> Result<O, Error>
+    where
+        T: Clone + Serialize + 'static,
+    {
+        let output = f(&mut self.state.value);
+        if !self.subscribed {
+            return Ok(output);
+        }
+        if let Some((message, new_json)) = self.state.next_message()? {
+            self.state.commit(&message, new_json)?;
+            batch.push(message);
+        }
+        Ok(output)
+    }
+
+    /// Sends the full value through the websocket connection as a snapshot.
+    ///
+    /// This should be called when the connection is established, so the client starts from
+    /// the current value rather than `T::default()`.
+    /// It is called automatically by [`ServerSignal::with`] if no snapshot has been sent yet.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// let count = ServerSignal::new("counter").unwrap();
+    /// count.send_snapshot(&mut websocket).await?;
+    /// ```
+    pub async fn send_snapshot<W>(&mut self, transport: &mut W) -> Result<(), Error>
+    where
+        W: Transport,
+    {
+        let snapshot = self.state.snapshot();
+        self.send(transport, &snapshot.into()).await?;
+        self.state.synced = true;
+        Ok(())
+    }
+
+    /// Handles a message received from the client, returning `true` if it was for this signal.
+    ///
+    /// Resync requests are answered with a snapshot of the full value,
+    /// and updates written by the client are passed to [`ServerSignal::apply_update`].
+    /// Subscribe requests start sending changes to [lazy](ServerSignal::lazy) signals with a snapshot,
+    /// and unsubscribe requests stop sending changes after the client's last reader of the signal is disposed.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// if let Some(Ok(message)) = websocket.recv().await {
+    ///     if let Ok(frame) = Frame::try_from(message) {
+    ///         let message = codec.decode_client(&frame)?;
+    ///         count.handle_message(&mut websocket, &message).await?;
+    ///     }
+    /// }
+    /// ```
+    pub async fn handle_message<W>(
+        &mut self,
+        transport: &mut W,
+        message: &ClientSignalMessage,
+    ) -> Result<bool, Error>
+    where
+        T: Serialize + for<'de> Deserialize<'de>,
+        W: Transport,
+    {
+        if message.name() != self.state.name {
+            return Ok(false);
+        }
+        match message {
+            ClientSignalMessage::Subscribe { .. } => {
+                if !self.subscribed {
+                    self.subscribe()?;
+                    self.send_snapshot(transport).await?;
+                }
+            }
+            ClientSignalMessage::Resync { .. } => {
+                if !self.subscribed {
+                    self.subscribe()?;
+                }
+                self.send_snapshot(transport).await?
+            }
+            ClientSignalMessage::Unsubscribe { .. } => self.subscribed = false,
+            ClientSignalMessage::Update(update) => {
+                self.apply_update(transport, update).await?;
+            }
+        }
+        Ok(true)
+    }
+
+    /// Starts sending changes to the client again.
+    fn subscribe(&mut self) -> Result<(), serde_json::Error>
+    where
+        T: Serialize,
+    {
+        // Changes made while unsubscribed were not sent, so the json value is out of date
+        self.state.rebase()?;
+        self.subscribed = true;
+        Ok(())
+    }
+
+    /// Applies an update written by the client, returning `true` if it was accepted.
+    ///
+    /// Accepted updates are sent back through the websocket connection with the next version,
+    /// confirming the change to the client.
+    /// Rejected updates are answered with a snapshot, reverting the change on the client.
+    /// Updates are rejected if the signal is not [writable](ServerSignal::writable),
+    /// the update was written against an older version than the current one,
+    /// the patch cannot be applied, or the patched value fails validation.
+    /// Updates which change nothing are also answered with a snapshot.
+    pub async fn apply_update<W>(
+        &mut self,
+        transport: &mut W,
+        update: &ServerSignalUpdate,
+    ) -> Result<bool, Error>
+    where
+        T: Serialize + for<'de> Deserialize<'de>,
+        W: Transport,
+    {
+        // Pending changes are sent first, so the update is applied on top of the current value
+        self.flush(transport).await?;
+
+        if !self.state.accept(update) {
+            self.send_snapshot(transport).await?;
+            return Ok(false);
+        }
+        match self.state.next_message()? {
+            Some((message, new_json)) => {
+                self.send(transport, &message).await?;
+                self.state.commit(&message, new_json)?;
+            }
+            // The client still waits for a reply to its update
+            None => self.send_snapshot(transport).await?,
+        }
+        Ok(true)
+    }
+
+    /// Sends a batch of messages through the websocket connection as a single frame.
+    ///
+    /// The batch is encoded with this signal's codec, and may contain messages for any signal
+    /// sharing the same websocket connection.
+    pub async fn send_batch<W>(
+        &self,
+        transport: &mut W,
+        batch: ServerSignalBatch,
+    ) -> Result<(), Error>
+    where
+        W: Transport,
+    {
+        if batch.is_empty() {
+            return Ok(());
+        }
+        self.send(transport, &batch.into()).await
+    }
+
+    async fn send<W>(&self, transport: &mut W, message: &ServerSignalMessage) -> Result<(), Error>
+    where
+        W: Transport,
+    {
+        let frame = self.state.encode(self.codec.as_ref(), message)?;
+        transport
+            .send_frame(frame)
+            .await
+            .map_err(|err| Error::Transport(Box::new(err)))
+    }
+
+    /// Returns a snapshot of the current value and version.
+    ///
+    /// This can be passed to [`provide_initial_snapshot`](crate::provide_initial_snapshot)
+    /// while rendering on the server, so the client starts from the current value.
+    pub fn snapshot(&self) -> ServerSignalSnapshot {
+        self.state.snapshot()
+    }
+
+    /// Returns `true` if changes are being sent to the client.
+    ///
+    /// This is `false` for [lazy](ServerSignal::lazy) signals until the client subscribes,
+    /// and after the client unsubscribes, so computing new values can be skipped.
+    pub fn is_subscribed(&self) -> bool {
+        self.subscribed
+    }
+
+    /// Consumes the [`ServerSignal`], returning the inner value.
+    pub fn into_value(self) -> T {
+        self.state.value
+    }
+
+    /// Consumes the [`ServerSignal`], returning the inner json value.
+    pub fn into_json_value(self) -> Value {
+        self.state.json_value
+    }
+}
+
+impl<T> ops::Deref for ServerSignal<T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        &self.state.value
+    }
+}
+
+impl<T> AsRef<T> for ServerSignal<T> {
+    fn as_ref(&self) -> &T {
+        &self.state.value
+    }
+}
+
+/// A server signal error.
+#[derive(Debug, Error)]
+pub enum Error {
+    /// Serialization of the signal value failed.
+    #[error(transparent)]
+    SerializationFailed(#[from] serde_json::Error),
+    /// Encoding of a message failed.
+    #[error(transparent)]
+    Codec(#[from] CodecError),
+    /// The transport failed to send a frame.
+    #[error(transparent)]
+    Transport(Box<dyn StdError + Send + Sync>),
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::channel::mpsc;
+    use futures::executor::block_on;
+
+    use super::*;
+    use crate::fixtures::{client_update, received, Count};
+
+    /// Returns a writable signal which has sent a snapshot and one update, at version 1.
+    fn writable_signal(tx: &mut UnboundedSender<Frame>) -> ServerSignal<Count> {
+        let mut count = ServerSignal::<Count>::new("count")
+            .unwrap()
+            .writable(|count| count.value >= 0);
+        block_on(count.send_snapshot(tx)).unwrap();
+        block_on(count.with(tx, |count| count.value = 1)).unwrap();
+        count
+    }
+
+    #[test]
+    fn unchanged_value_is_not_sent() {
+        let (mut tx, mut rx) = mpsc::unbounded();
+        let mut count = writable_signal(&mut tx);
+        received(&mut rx);
+
+        block_on(count.with(&mut tx, |count| count.value = 1)).unwrap();
+        assert!(received(&mut rx).is_empty());
+
+        block_on(count.with(&mut tx, |count| count.value = 2)).unwrap();
+        match received(&mut rx).as_slice() {
+            [ServerSignalMessage::Update(update)] => assert_eq!(update.version(), 2),
+            messages => panic!("expected an update, got {messages:?}"),
+        }
+    }
+
+    #[test]
+    fn unchanged_value_is_not_batched() {
+        let (mut tx, mut rx) = mpsc::unbounded();
+        let mut count = writable_signal(&mut tx);
+        received(&mut rx);
+
+        let mut batch = ServerSignalBatch::new();
+        count.with_batched(&mut batch, |_| {}).unwrap();
+        assert!(batch.is_empty());
+        count
+            .with_batched(&mut batch, |count| count.value = 2)
+            .unwrap();
+        assert_eq!(batch.messages()[0].version(), Some(2));
+    }
+
+    #[test]
+    fn unchanged_value_with_snapshot_threshold_is_not_sent() {
+        let (mut tx, mut rx) = mpsc::unbounded();
+        let mut count = ServerSignal::<Count>::new("count")
+            .unwrap()
+            .snapshot_threshold(0.0);
+        block_on(count.send_snapshot(&mut tx)).unwrap();
+        received(&mut rx);
+
+        block_on(count.with(&mut tx, |_| {})).unwrap();
+        assert!(received(&mut rx).is_empty());
+    }
+
+    #[test]
+    fn throttled_changes_are_coalesced() {
+        let (mut tx, mut rx) = mpsc::unbounded();
+        let mut count = ServerSignal::<Count>::new("count")
+            .unwrap()
+            .throttle(Duration::from_secs(60));
+        block_on(count.with(&mut tx, |count| count.value = 1)).unwrap();
+        assert!(matches!(
+            received(&mut rx).as_slice(),
+            [ServerSignalMessage::Snapshot(_)]
+        ));
+
+        block_on(count.with(&mut tx, |count| count.value = 2)).unwrap();
+        block_on(count.with(&mut tx, |count| count.value = 3)).unwrap();
+        assert!(received(&mut rx).is_empty());
+
+        block_on(count.flush(&mut tx)).unwrap();
+        match received(&mut rx).as_slice() {
+            [ServerSignalMessage::Update(update)] => {
+                assert_eq!(update.version(), 2);
+                let mut json = serde_json::json!({ "value": 1 });
+                json_patch::patch(&mut json, update.patch()).unwrap();
+                assert_eq!(json, serde_json::json!({ "value": 3 }));
+            }
+            messages => panic!("expected an update, got {messages:?}"),
+        }
+        block_on(count.flush(&mut tx)).unwrap();
+        assert!(received(&mut rx).is_empty());
+    }
+
+    #[test]
+    fn apply_update_accepts_current_version() {
+        let (mut tx, mut rx) = mpsc::unbounded();
+        let mut count = writable_signal(&mut tx);
+        received(&mut rx);
+
+        let accepted = block_on(count.apply_update(&mut tx, &client_update(1, 1, 5))).unwrap();
+        assert!(accepted);
+        assert_eq!(count.as_ref(), &Count { value: 5 });
+        match received(&mut rx).as_slice() {
+            [ServerSignalMessage::Update(update)] => assert_eq!(update.version(), 2),
+            messages => panic!("expected an update, got {messages:?}"),
+        }
+    }
+
+    #[test]
+    fn apply_update_answers_rejection_with_snapshot() {
+        let (mut tx, mut rx) = mpsc::unbounded();
+        let mut count = writable_signal(&mut tx);
+        received(&mut rx);
+
+        let accepted = block_on(count.apply_update(&mut tx, &client_update(0, 0, 5))).unwrap();
+        assert!(!accepted);
+        assert_eq!(count.as_ref(), &Count { value: 1 });
+        match received(&mut rx).as_slice() {
+            [ServerSignalMessage::Snapshot(snapshot)] => {
+                assert_eq!(snapshot.version(), 1);
+                assert_eq!(snapshot.value(), &serde_json::json!({ "value": 1 }));
+            }
+            messages => panic!("expected a snapshot, got {messages:?}"),
+        }
+    }
+
+    #[test]
+    fn apply_update_answers_unchanged_value_with_snapshot() {
+        let (mut tx, mut rx) = mpsc::unbounded();
+        let mut count = writable_signal(&mut tx);
+        received(&mut rx);
+
+        let accepted = block_on(count.apply_update(&mut tx, &client_update(1, 0, 1))).unwrap();
+        assert!(accepted);
+        match received(&mut rx).as_slice() {
+            [ServerSignalMessage::Snapshot(snapshot)] => assert_eq!(snapshot.version(), 1),
+            messages => panic!("expected a snapshot, got {messages:?}"),
+        }
+    }
+}
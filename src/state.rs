@@ -0,0 +1,315 @@
+use std::borrow::Cow;
+use std::sync::Arc;
+
+use json_patch::Patch;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::strategy::exceeds_threshold;
+#[cfg(feature = "compression")]
+use crate::Compression;
+use crate::{
+    Codec, CodecError, Diff, DiffStrategy, Frame, JsonDiff, ServerSignalMessage,
+    ServerSignalSnapshot, ServerSignalUpdate, Throttle, Validator,
+};
+
+/// The value of a server signal and the last value sent to its clients.
+///
+/// This computes the messages sent by [`ServerSignal`](crate::ServerSignal) and
+/// [`ServerSignalHub`](crate::ServerSignalHub), which only differ in how the messages are delivered.
+#[derive(Clone, Debug)]
+pub(crate) struct SignalState<T> {
+    pub(crate) name: Cow<'static, str>,
+    pub(crate) value: T,
+    // The last json value sent to clients
+    pub(crate) json_value: Value,
+    pub(crate) version: u64,
+    // Set once clients have the json value, so changes can be sent as diffs
+    pub(crate) synced: bool,
+    pub(crate) validator: Option<Validator<T>>,
+    pub(crate) throttle: Throttle,
+    pub(crate) tracked: Option<Tracked<T>>,
+    pub(crate) strategy: Arc<dyn DiffStrategy>,
+    pub(crate) snapshot_threshold: Option<f64>,
+    #[cfg(feature = "compression")]
+    pub(crate) compression: Option<Compression>,
+}
+
+/// The value last sent to clients, which [`Diff`] compares against.
+#[derive(Clone, Debug)]
+pub(crate) struct Tracked<T> {
+    diff: fn(&T, &T, &str, &mut Patch) -> Result<(), serde_json::Error>,
+    clone: fn(&T) -> T,
+    sent: T,
+}
+
+impl<T> SignalState<T> {
+    pub(crate) fn new(name: Cow<'static, str>, value: T) -> Result<Self, serde_json::Error>
+    where
+        T: Serialize,
+    {
+        let json_value = serde_json::to_value(&value)?;
+        Ok(SignalState {
+            name,
+            value,
+            json_value,
+            version: 0,
+            synced: false,
+            validator: None,
+            throttle: Throttle::default(),
+            tracked: None,
+            strategy: Arc::new(JsonDiff),
+            snapshot_threshold: None,
+            #[cfg(feature = "compression")]
+            compression: None,
+        })
+    }
+
+    /// Computes diffs with the value's [`Diff`] implementation.
+    pub(crate) fn track(&mut self)
+    where
+        T: Diff + Clone,
+    {
+        self.tracked = Some(Tracked {
+            diff: T::diff,
+            clone: T::clone,
+            sent: self.value.clone(),
+        });
+    }
+
+    pub(crate) fn snapshot(&self) -> ServerSignalSnapshot {
+        ServerSignalSnapshot::new_from_json(self.name.clone(), self.json_value.clone())
+            .with_version(self.version)
+    }
+
+    /// Returns the message bringing clients from the last sent value to the current value,
+    /// along with the new json value if it was serialized in full.
+    ///
+    /// If clients are not synced yet, or the diff exceeds the snapshot threshold,
+    /// this is a snapshot instead of a diff. Returns `None` if nothing changed since the last sent value.
+    pub(crate) fn next_message(
+        &self,
+    ) -> Result<Option<(ServerSignalMessage, Option<Value>)>, serde_json::Error>
+    where
+        T: Serialize,
+    {
+        let version = self.version + 1;
+        match &self.tracked {
+            Some(tracked) if self.synced => {
+                let mut patch = Patch(Vec::new());
+                (tracked.diff)(&self.value, &tracked.sent, "", &mut patch)?;
+                if patch.0.is_empty() {
+                    return Ok(None);
+                }
+                let update = ServerSignalUpdate::new_from_patch(self.name.clone(), patch)
+                    .with_version(version);
+                Ok(Some((update.into(), None)))
+            }
+            _ => {
+                let new_json = serde_json::to_value(&self.value)?;
+                let patch = self
+                    .synced
+                    .then(|| self.strategy.diff(&self.json_value, &new_json))
+                    .filter(|patch| {
+                        patch.0.is_empty()
+                            || !self.snapshot_threshold.is_some_and(|threshold| {
+                                exceeds_threshold(threshold, patch, &new_json)
+                            })
+                    });
+                let message = match patch {
+                    Some(patch) if patch.0.is_empty() => return Ok(None),
+                    Some(patch) => ServerSignalUpdate::new_from_patch(self.name.clone(), patch)
+                        .with_version(version)
+                        .into(),
+                    None => {
+                        ServerSignalSnapshot::new_from_json(self.name.clone(), new_json.clone())
+                            .with_version(version)
+                            .into()
+                    }
+                };
+                Ok(Some((message, Some(new_json))))
+            }
+        }
+    }
+
+    /// Records a message from [`SignalState::next_message`] as sent.
+    ///
+    /// Without the new json value, the message's patch is applied to the current json value instead.
+    pub(crate) fn commit(
+        &mut self,
+        message: &ServerSignalMessage,
+        new_json: Option<Value>,
+    ) -> Result<(), serde_json::Error>
+    where
+        T: Serialize,
+    {
+        match new_json {
+            Some(new_json) => self.json_value = new_json,
+            None => {
+                if message.apply(&mut self.json_value).is_err() {
+                    self.json_value = serde_json::to_value(&self.value)?;
+                }
+            }
+        }
+        self.sync_tracked();
+        self.version += 1;
+        self.synced = true;
+        self.throttle.sent();
+        Ok(())
+    }
+
+    /// Records the current value as the last sent value, after changes were made without sending them.
+    pub(crate) fn rebase(&mut self) -> Result<(), serde_json::Error>
+    where
+        T: Serialize,
+    {
+        self.json_value = serde_json::to_value(&self.value)?;
+        self.sync_tracked();
+        Ok(())
+    }
+
+    /// Records the current value as the last sent value compared against by [`Diff`].
+    fn sync_tracked(&mut self) {
+        if let Some(tracked) = &mut self.tracked {
+            tracked.sent = (tracked.clone)(&self.value);
+        }
+    }
+
+    /// Replaces the value with an update written by a client, returning `true` if it was accepted.
+    ///
+    /// Updates are rejected if the signal is not writable, the update was written against an older
+    /// version than the current one, the patch cannot be applied, or the patched value fails validation.
+    /// Pending changes should be sent first, so the update is applied on top of the current value.
+    /// The accepted change is sent like any other, by [`SignalState::next_message`].
+    pub(crate) fn accept(&mut self, update: &ServerSignalUpdate) -> bool
+    where
+        T: for<'de> Deserialize<'de>,
+    {
+        // Updates written against an older value would overwrite changes the client has not seen
+        let accepted = self
+            .validator
+            .as_ref()
+            .filter(|_| update.version() == self.version)
+            .and_then(|validator| {
+                let (_, value) = update.apply_to::<T>(&self.json_value)?;
+                validator.validate(&value).then_some(value)
+            });
+        match accepted {
+            Some(value) => {
+                self.value = value;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Encodes a message with `codec`, compressing it if enabled.
+    pub(crate) fn encode(
+        &self,
+        codec: &dyn Codec,
+        message: &ServerSignalMessage,
+    ) -> Result<Frame, CodecError> {
+        let frame = codec.encode_server(message)?;
+        #[cfg(feature = "compression")]
+        let frame = match &self.compression {
+            Some(compression) => compression.compress(frame)?,
+            None => frame,
+        };
+        Ok(frame)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::fixtures::{client_update, Count};
+
+    /// Returns a writable state which clients have synced to, at version 1.
+    fn writable_state() -> SignalState<Count> {
+        let mut state = SignalState::new("count".into(), Count { value: 1 }).unwrap();
+        state.validator = Some(Validator::new(|count: &Count| count.value >= 0));
+        state.synced = true;
+        state.version = 1;
+        state
+    }
+
+    fn updated_json(state: &SignalState<Count>) -> Value {
+        let (message, _) = state.next_message().unwrap().expect("a message");
+        let mut json = state.json_value.clone();
+        message.apply(&mut json).unwrap();
+        json
+    }
+
+    #[test]
+    fn accept_applies_update_written_against_current_version() {
+        let mut state = writable_state();
+        assert!(state.accept(&client_update(1, 1, 5)));
+        assert_eq!(state.value, Count { value: 5 });
+
+        let (message, _) = state.next_message().unwrap().unwrap();
+        assert!(matches!(message, ServerSignalMessage::Update(_)));
+        assert_eq!(message.version(), Some(2));
+        assert_eq!(updated_json(&state), json!({ "value": 5 }));
+    }
+
+    #[test]
+    fn accept_rejects_older_version() {
+        let mut state = writable_state();
+        assert!(!state.accept(&client_update(0, 0, 5)));
+        assert_eq!(state.value, Count { value: 1 });
+        assert!(state.next_message().unwrap().is_none());
+    }
+
+    #[test]
+    fn accept_rejects_invalid_value() {
+        let mut state = writable_state();
+        assert!(!state.accept(&client_update(1, 1, -1)));
+        assert_eq!(state.value, Count { value: 1 });
+    }
+
+    #[test]
+    fn accept_rejects_updates_without_validator() {
+        let mut state = writable_state();
+        state.validator = None;
+        assert!(!state.accept(&client_update(1, 1, 5)));
+        assert_eq!(state.value, Count { value: 1 });
+    }
+
+    #[test]
+    fn accept_unchanged_value_sends_nothing() {
+        let mut state = writable_state();
+        assert!(state.accept(&client_update(1, 0, 1)));
+        assert!(state.next_message().unwrap().is_none());
+    }
+
+    #[test]
+    fn next_message_is_snapshot_until_synced() {
+        let mut state = SignalState::new("count".into(), Count::default()).unwrap();
+        state.value.value = 1;
+        let (message, new_json) = state.next_message().unwrap().unwrap();
+        assert!(matches!(message, ServerSignalMessage::Snapshot(_)));
+        state.commit(&message, new_json).unwrap();
+
+        state.value.value = 2;
+        let (message, _) = state.next_message().unwrap().unwrap();
+        assert!(matches!(message, ServerSignalMessage::Update(_)));
+        assert_eq!(message.version(), Some(2));
+    }
+
+    #[test]
+    fn tracked_state_diffs_against_last_sent_value() {
+        let mut state = writable_state();
+        state.track();
+        state.value.value = 3;
+
+        // The patch comes from the Diff implementation, without the json value
+        let (message, new_json) = state.next_message().unwrap().unwrap();
+        assert!(new_json.is_none());
+        assert_eq!(updated_json(&state), json!({ "value": 3 }));
+        state.commit(&message, new_json).unwrap();
+        assert_eq!(state.json_value, json!({ "value": 3 }));
+        assert!(state.next_message().unwrap().is_none());
+    }
+}
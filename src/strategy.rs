@@ -0,0 +1,436 @@
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::fmt;
+use std::io;
+
+use json_patch::jsonptr::PointerBuf;
+use json_patch::{
+    AddOperation, MoveOperation, Patch, PatchOperation, RemoveOperation, ReplaceOperation,
+};
+use serde::Serialize;
+use serde_json::Value;
+
+/// Computes the json patch sent to clients when a server signal changes.
+///
+/// The strategy is set with `diff_strategy` on a `ServerSignal` or `ServerSignalHub`,
+/// and defaults to [`JsonDiff`].
+///
+/// # Example
+///
+/// ```ignore
+/// let todos = ServerSignal::<Todos>::new("todos")
+///     .unwrap()
+///     .diff_strategy(Arc::new(KeyedDiff::new("id")));
+/// ```
+pub trait DiffStrategy: fmt::Debug + Send + Sync + 'static {
+    /// Returns the patch turning `old` into `new`, which is empty if they are equal.
+    fn diff(&self, old: &Value, new: &Value) -> Patch;
+}
+
+/// Diffs values with [`json_patch::diff`].
+///
+/// This is the default strategy. Arrays are compared by index, so inserting or reordering
+/// elements rewrites every element after the change.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct JsonDiff;
+
+impl DiffStrategy for JsonDiff {
+    fn diff(&self, old: &Value, new: &Value) -> Patch {
+        json_patch::diff(old, new)
+    }
+}
+
+/// Diffs arrays of objects by a key field, producing `move`, `add` and `remove` operations
+/// for inserted, reordered and removed elements.
+///
+/// Arrays whose elements are not all objects with a unique key are compared by index.
+///
+/// # Example
+///
+/// ```
+/// # use leptos_server_signal::{DiffStrategy, KeyedDiff};
+/// # use serde_json::json;
+/// let old = json!([{ "id": 1, "done": false }, { "id": 2, "done": false }]);
+/// let new = json!([{ "id": 0, "done": false }, { "id": 1, "done": true }, { "id": 2, "done": false }]);
+///
+/// // An add for the new element, and a replace of `done`, without rewriting the last element
+/// let patch = KeyedDiff::new("id").diff(&old, &new);
+/// assert_eq!(patch.0.len(), 2);
+/// ```
+#[derive(Clone, Debug)]
+pub struct KeyedDiff {
+    key: Cow<'static, str>,
+}
+
+impl KeyedDiff {
+    /// Creates a new [`KeyedDiff`], identifying array elements by the `key` field.
+    pub fn new(key: impl Into<Cow<'static, str>>) -> Self {
+        KeyedDiff { key: key.into() }
+    }
+
+    fn diff_value(&self, old: &Value, new: &Value, path: &PointerBuf, patch: &mut Patch) {
+        match (old, new) {
+            (Value::Object(old), Value::Object(new)) => {
+                for key in old.keys().filter(|key| !new.contains_key(*key)) {
+                    patch.0.push(PatchOperation::Remove(RemoveOperation {
+                        path: child(path, key.as_str()),
+                    }));
+                }
+                for (key, new_value) in new {
+                    match old.get(key) {
+                        Some(old_value) => {
+                            self.diff_value(old_value, new_value, &child(path, key.as_str()), patch)
+                        }
+                        None => patch.0.push(PatchOperation::Add(AddOperation {
+                            path: child(path, key.as_str()),
+                            value: new_value.clone(),
+                        })),
+                    }
+                }
+            }
+            (Value::Array(old), Value::Array(new)) => match (self.keys(old), self.keys(new)) {
+                (Some(old_keys), Some(new_keys)) => {
+                    self.diff_keyed(old, old_keys, new, new_keys, path, patch)
+                }
+                _ => self.diff_indexed(old, new, path, patch),
+            },
+            _ if old != new => patch.0.push(PatchOperation::Replace(ReplaceOperation {
+                path: path.clone(),
+                value: new.clone(),
+            })),
+            _ => {}
+        }
+    }
+
+    /// Diffs arrays by index, like [`json_patch::diff`].
+    fn diff_indexed(&self, old: &[Value], new: &[Value], path: &PointerBuf, patch: &mut Patch) {
+        for (index, (old_value, new_value)) in old.iter().zip(new).enumerate() {
+            self.diff_value(old_value, new_value, &child(path, index), patch);
+        }
+        for index in (new.len()..old.len()).rev() {
+            patch.0.push(PatchOperation::Remove(RemoveOperation {
+                path: child(path, index),
+            }));
+        }
+        for (index, new_value) in new.iter().enumerate().skip(old.len()) {
+            patch.0.push(PatchOperation::Add(AddOperation {
+                path: child(path, index),
+                value: new_value.clone(),
+            }));
+        }
+    }
+
+    /// Diffs arrays by key, removing, moving and inserting elements until the order matches.
+    fn diff_keyed(
+        &self,
+        old: &[Value],
+        old_keys: Vec<String>,
+        new: &[Value],
+        new_keys: Vec<String>,
+        path: &PointerBuf,
+        patch: &mut Patch,
+    ) {
+        let old_values: HashMap<&str, &Value> =
+            old_keys.iter().map(String::as_str).zip(old).collect();
+        let new_indices: HashMap<&str, usize> = new_keys
+            .iter()
+            .enumerate()
+            .map(|(index, key)| (key.as_str(), index))
+            .collect();
+
+        // The keys of the array on the client as each operation is applied
+        let mut current: Vec<&str> = old_keys.iter().map(String::as_str).collect();
+        for index in (0..current.len()).rev() {
+            if !new_indices.contains_key(current[index]) {
+                patch.0.push(PatchOperation::Remove(RemoveOperation {
+                    path: child(path, index),
+                }));
+                current.remove(index);
+            }
+        }
+
+        for (index, (key, new_value)) in new_keys.iter().zip(new).enumerate() {
+            let Some(old_value) = old_values.get(key.as_str()) else {
+                patch.0.push(PatchOperation::Add(AddOperation {
+                    path: child(path, index),
+                    value: new_value.clone(),
+                }));
+                current.insert(index, key.as_str());
+                continue;
+            };
+            if current[index] != key.as_str() {
+                // Elements before `index` are already in place, so the element is further along
+                let from = current
+                    .iter()
+                    .position(|current| *current == key.as_str())
+                    .unwrap();
+                patch.0.push(PatchOperation::Move(MoveOperation {
+                    from: child(path, from),
+                    path: child(path, index),
+                }));
+                current.remove(from);
+                current.insert(index, key.as_str());
+            }
+            self.diff_value(old_value, new_value, &child(path, index), patch);
+        }
+    }
+
+    /// Returns the key of each element, or `None` if any element has no key or a duplicate key.
+    fn keys(&self, values: &[Value]) -> Option<Vec<String>> {
+        let keys: Vec<String> = values
+            .iter()
+            .map(|value| value.get(self.key.as_ref()).map(Value::to_string))
+            .collect::<Option<_>>()?;
+        let mut unique: Vec<&String> = keys.iter().collect();
+        unique.sort_unstable();
+        unique.dedup();
+        (unique.len() == keys.len()).then_some(keys)
+    }
+}
+
+impl DiffStrategy for KeyedDiff {
+    fn diff(&self, old: &Value, new: &Value) -> Patch {
+        let mut patch = Patch(Vec::new());
+        self.diff_value(old, new, &PointerBuf::root(), &mut patch);
+        patch
+    }
+}
+
+/// Replaces the whole value whenever it changes.
+///
+/// This suits small values, or values which change entirely with each update.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ReplaceDiff;
+
+impl DiffStrategy for ReplaceDiff {
+    fn diff(&self, old: &Value, new: &Value) -> Patch {
+        if old == new {
+            return Patch(Vec::new());
+        }
+        Patch(vec![PatchOperation::Replace(ReplaceOperation {
+            path: PointerBuf::root(),
+            value: new.clone(),
+        })])
+    }
+}
+
+/// Returns the pointer to a child of the value at `path`.
+fn child(path: &PointerBuf, token: impl fmt::Display) -> PointerBuf {
+    let mut path = path.clone();
+    path.push_back(token.to_string());
+    path
+}
+
+/// Moves every operation of a patch under a child of the root, so it applies to that child instead.
+pub(crate) fn nest(mut patch: Patch, token: impl fmt::Display) -> Patch {
+    let token = token.to_string();
+    for operation in &mut patch.0 {
+        match operation {
+            PatchOperation::Add(operation) => operation.path.push_front(&token),
+            PatchOperation::Remove(operation) => operation.path.push_front(&token),
+            PatchOperation::Replace(operation) => operation.path.push_front(&token),
+            PatchOperation::Test(operation) => operation.path.push_front(&token),
+            PatchOperation::Move(operation) => {
+                operation.from.push_front(&token);
+                operation.path.push_front(&token);
+            }
+            PatchOperation::Copy(operation) => {
+                operation.from.push_front(&token);
+                operation.path.push_front(&token);
+            }
+        }
+    }
+    patch
+}
+
+/// Returns `true` if the json-encoded size of `patch` exceeds `threshold` times the size of `value`.
+pub(crate) fn exceeds_threshold(threshold: f64, patch: &Patch, value: &Value) -> bool {
+    encoded_len(patch) as f64 > threshold * encoded_len(value) as f64
+}
+
+/// Returns the length of a value encoded as json, without allocating the encoded string.
+fn encoded_len(value: &impl Serialize) -> usize {
+    struct Counter(usize);
+
+    impl io::Write for Counter {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0 += buf.len();
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    let mut counter = Counter(0);
+    match serde_json::to_writer(&mut counter, value) {
+        Ok(()) => counter.0,
+        Err(_) => usize::MAX,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    /// Applies the diff to the old value, asserting it produces the new value.
+    fn assert_diff_applies(strategy: &dyn DiffStrategy, old: &Value, new: &Value) -> Patch {
+        let patch = strategy.diff(old, new);
+        let mut json = old.clone();
+        json_patch::patch(&mut json, &patch).unwrap();
+        assert_eq!(&json, new);
+        patch
+    }
+
+    fn ops(patch: &Patch) -> Vec<String> {
+        patch
+            .0
+            .iter()
+            .map(|operation| format!("{} {}", operation_name(operation), operation.path()))
+            .collect()
+    }
+
+    fn operation_name(operation: &PatchOperation) -> &'static str {
+        match operation {
+            PatchOperation::Add(_) => "add",
+            PatchOperation::Remove(_) => "remove",
+            PatchOperation::Replace(_) => "replace",
+            PatchOperation::Move(_) => "move",
+            PatchOperation::Copy(_) => "copy",
+            PatchOperation::Test(_) => "test",
+        }
+    }
+
+    #[test]
+    fn keyed_diff_is_empty_for_equal_values() {
+        let value = json!({ "todos": [{ "id": 1 }, { "id": 2 }] });
+        assert!(KeyedDiff::new("id").diff(&value, &value).0.is_empty());
+    }
+
+    #[test]
+    fn keyed_diff_inserts_without_rewriting_later_elements() {
+        let old = json!([{ "id": 1, "done": false }, { "id": 2, "done": false }]);
+        let new = json!([{ "id": 0, "done": false }, { "id": 1, "done": false }, { "id": 2, "done": false }]);
+        let patch = assert_diff_applies(&KeyedDiff::new("id"), &old, &new);
+        assert_eq!(ops(&patch), ["add /0"]);
+    }
+
+    #[test]
+    fn keyed_diff_removes_elements() {
+        let old = json!([{ "id": 1 }, { "id": 2 }, { "id": 3 }, { "id": 4 }]);
+        let new = json!([{ "id": 1 }, { "id": 3 }]);
+        let patch = assert_diff_applies(&KeyedDiff::new("id"), &old, &new);
+        // Removed from the end first, so earlier indices stay valid
+        assert_eq!(ops(&patch), ["remove /3", "remove /1"]);
+    }
+
+    #[test]
+    fn keyed_diff_moves_reordered_elements() {
+        let old = json!([{ "id": 1 }, { "id": 2 }, { "id": 3 }]);
+        let new = json!([{ "id": 3 }, { "id": 1 }, { "id": 2 }]);
+        let patch = assert_diff_applies(&KeyedDiff::new("id"), &old, &new);
+        assert_eq!(ops(&patch), ["move /0"]);
+    }
+
+    #[test]
+    fn keyed_diff_handles_combined_changes() {
+        let old = json!([
+            { "id": "a", "n": 1 },
+            { "id": "b", "n": 2 },
+            { "id": "c", "n": 3 },
+            { "id": "d", "n": 4 },
+        ]);
+        let new = json!([
+            { "id": "d", "n": 4 },
+            { "id": "e", "n": 5 },
+            { "id": "b", "n": 20 },
+            { "id": "a", "n": 1 },
+        ]);
+        assert_diff_applies(&KeyedDiff::new("id"), &old, &new);
+    }
+
+    #[test]
+    fn keyed_diff_diffs_moved_elements() {
+        let old = json!([{ "id": 1, "done": false }, { "id": 2, "done": false }]);
+        let new = json!([{ "id": 2, "done": true }, { "id": 1, "done": false }]);
+        let patch = assert_diff_applies(&KeyedDiff::new("id"), &old, &new);
+        assert_eq!(ops(&patch), ["move /0", "replace /0/done"]);
+    }
+
+    #[test]
+    fn keyed_diff_compares_duplicate_keys_by_index() {
+        let old = json!([{ "id": 1, "n": 1 }, { "id": 1, "n": 2 }]);
+        let new = json!([{ "id": 1, "n": 2 }, { "id": 1, "n": 1 }]);
+        let patch = assert_diff_applies(&KeyedDiff::new("id"), &old, &new);
+        assert_eq!(ops(&patch), ["replace /0/n", "replace /1/n"]);
+    }
+
+    #[test]
+    fn keyed_diff_compares_unkeyed_arrays_by_index() {
+        let old = json!([1, 2, 3]);
+        let new = json!([1, 4]);
+        let patch = assert_diff_applies(&KeyedDiff::new("id"), &old, &new);
+        assert_eq!(ops(&patch), ["replace /1", "remove /2"]);
+
+        let old = json!([{ "id": 1 }, { "name": "missing key" }]);
+        let new = json!([{ "name": "missing key" }, { "id": 1 }]);
+        assert_diff_applies(&KeyedDiff::new("id"), &old, &new);
+    }
+
+    #[test]
+    fn keyed_diff_diffs_nested_objects() {
+        let old = json!({ "user": { "name": "ari", "tags": [{ "id": 1 }] }, "old": true });
+        let new =
+            json!({ "user": { "name": "bo", "tags": [{ "id": 2 }, { "id": 1 }] }, "new": true });
+        let patch = assert_diff_applies(&KeyedDiff::new("id"), &old, &new);
+        assert_eq!(
+            ops(&patch),
+            [
+                "remove /old",
+                "add /new",
+                "replace /user/name",
+                "add /user/tags/0"
+            ]
+        );
+    }
+
+    #[test]
+    fn keyed_diff_escapes_object_keys() {
+        let old = json!({ "a/b": 1, "c~d": 1 });
+        let new = json!({ "a/b": 2, "c~d": 2 });
+        let patch = assert_diff_applies(&KeyedDiff::new("id"), &old, &new);
+        assert_eq!(ops(&patch), ["replace /a~1b", "replace /c~0d"]);
+    }
+
+    #[test]
+    fn replace_diff_replaces_whole_value() {
+        let old = json!({ "count": 1 });
+        let new = json!({ "count": 2 });
+        let patch = assert_diff_applies(&ReplaceDiff, &old, &new);
+        assert_eq!(ops(&patch), ["replace "]);
+        assert!(ReplaceDiff.diff(&old, &old).0.is_empty());
+    }
+
+    #[test]
+    fn nest_moves_operations_under_child() {
+        let old = json!({ "count": 1, "items": [1, 2] });
+        let new = json!({ "count": 2, "items": [2] });
+        let patch = nest(JsonDiff.diff(&old, &new), 3);
+
+        let mut json = json!([null, null, null, old]);
+        json_patch::patch(&mut json, &patch).unwrap();
+        assert_eq!(json[3], new);
+    }
+
+    #[test]
+    fn threshold_compares_encoded_sizes() {
+        let value = json!({ "count": 1 });
+        let patch = JsonDiff.diff(&json!({ "count": 0 }), &value);
+        assert!(exceeds_threshold(0.5, &patch, &value));
+        assert!(!exceeds_threshold(10.0, &patch, &value));
+        assert_eq!(encoded_len(&value), r#"{"count":1}"#.len());
+    }
+}
@@ -0,0 +1,63 @@
+use std::borrow::Cow;
+use std::collections::HashSet;
+
+use crate::ClientSignalMessage;
+
+/// The names of the signals a client reads, tracked from its subscribe and unsubscribe requests.
+///
+/// Connection handlers keep one per connection, and pass it each message received from the client,
+/// so they can skip computing and sending signals which the page does not read.
+///
+/// # Example
+///
+/// ```
+/// # use leptos_server_signal::{ClientSignalMessage, Subscriptions};
+/// let mut subscriptions = Subscriptions::new();
+/// subscriptions.handle_message(&ClientSignalMessage::Subscribe { name: "counter".into() });
+///
+/// assert!(subscriptions.contains("counter"));
+/// assert!(!subscriptions.contains("messages"));
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct Subscriptions {
+    names: HashSet<Cow<'static, str>>,
+}
+
+impl Subscriptions {
+    /// Creates a new [`Subscriptions`] without any signals.
+    pub fn new() -> Self {
+        Subscriptions::default()
+    }
+
+    /// Updates the subscriptions from a message received from the client, returning `true` if they changed.
+    pub fn handle_message(&mut self, message: &ClientSignalMessage) -> bool {
+        match message {
+            // Resyncs are only requested for signals the client reads
+            ClientSignalMessage::Subscribe { name } | ClientSignalMessage::Resync { name } => {
+                self.names.insert(name.clone())
+            }
+            ClientSignalMessage::Unsubscribe { name } => self.names.remove(name),
+            _ => false,
+        }
+    }
+
+    /// Returns `true` if the client reads the signal.
+    pub fn contains(&self, name: &str) -> bool {
+        self.names.contains(name)
+    }
+
+    /// Returns the names of the signals the client reads.
+    pub fn iter(&self) -> impl Iterator<Item = &str> {
+        self.names.iter().map(AsRef::as_ref)
+    }
+
+    /// Returns the number of signals the client reads.
+    pub fn len(&self) -> usize {
+        self.names.len()
+    }
+
+    /// Returns `true` if the client reads no signals.
+    pub fn is_empty(&self) -> bool {
+        self.names.is_empty()
+    }
+}
@@ -0,0 +1,51 @@
+use futures::sink::SinkExt;
+use futures::stream::SplitSink;
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio_tungstenite::tungstenite::{self, Message};
+use tokio_tungstenite::WebSocketStream;
+
+use crate::{Frame, Transport};
+
+impl<S> Transport for WebSocketStream<S>
+where
+    S: AsyncRead + AsyncWrite + Unpin + Send,
+{
+    type Error = tungstenite::Error;
+
+    async fn send_frame(&mut self, frame: Frame) -> Result<(), Self::Error> {
+        self.send(frame.into()).await
+    }
+}
+
+impl<S> Transport for SplitSink<WebSocketStream<S>, Message>
+where
+    S: AsyncRead + AsyncWrite + Unpin + Send,
+{
+    type Error = tungstenite::Error;
+
+    async fn send_frame(&mut self, frame: Frame) -> Result<(), Self::Error> {
+        self.send(frame.into()).await
+    }
+}
+
+impl From<Frame> for Message {
+    fn from(frame: Frame) -> Self {
+        match frame {
+            Frame::Text(text) => Message::Text(text.into()),
+            Frame::Binary(bytes) => Message::Binary(bytes.into()),
+        }
+    }
+}
+
+impl TryFrom<Message> for Frame {
+    type Error = Message;
+
+    /// Converts a text or binary message into a frame, returning control messages as an error.
+    fn try_from(message: Message) -> Result<Self, Self::Error> {
+        match message {
+            Message::Text(text) => Ok(Frame::Text(text.to_string())),
+            Message::Binary(bytes) => Ok(Frame::Binary(bytes.to_vec())),
+            message => Err(message),
+        }
+    }
+}
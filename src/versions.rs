@@ -0,0 +1,161 @@
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
+
+use crate::{LongPollRequest, ServerSignalMessage};
+
+/// The last version received of each server signal, used to detect missed updates.
+#[derive(Debug, Default)]
+pub(crate) struct SignalVersions {
+    pub(crate) versions: HashMap<Cow<'static, str>, u64>,
+    // Signals which have requested a resync, and are waiting for a snapshot
+    pub(crate) resyncing: HashSet<Cow<'static, str>>,
+    // Signals which have no readers left, or whose queued messages were discarded,
+    // and are resynced when read again
+    pub(crate) unsubscribed: HashSet<Cow<'static, str>>,
+}
+
+/// What to do with a message received from the server.
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) enum VersionCheck {
+    Apply,
+    Ignore,
+    Resync,
+}
+
+impl SignalVersions {
+    /// Returns a long-polling request for the messages missed since the last received versions.
+    ///
+    /// Signals which are resyncing are left out, so a snapshot is sent for them.
+    pub(crate) fn long_poll_request(&self) -> LongPollRequest {
+        self.versions
+            .iter()
+            .filter(|(name, _)| !self.resyncing.contains(*name))
+            .fold(LongPollRequest::new(), |request, (name, version)| {
+                request.with_version(name.clone(), *version)
+            })
+    }
+
+    /// Checks whether a message follows the last received version of its signal.
+    ///
+    /// Snapshots are always applied. Updates are applied only if they are the next version,
+    /// stale updates are ignored, and a gap requests a resync until a snapshot is received.
+    pub(crate) fn check(&mut self, message: &ServerSignalMessage) -> VersionCheck {
+        match message {
+            ServerSignalMessage::Snapshot(snapshot) => {
+                self.resyncing.remove(&snapshot.name);
+                self.versions
+                    .insert(snapshot.name.clone(), snapshot.version);
+                VersionCheck::Apply
+            }
+            ServerSignalMessage::Update(update) if self.resyncing.contains(&update.name) => {
+                VersionCheck::Ignore
+            }
+            ServerSignalMessage::Update(update) => match self.versions.get_mut(&update.name) {
+                Some(version) if update.version == *version + 1 => {
+                    *version = update.version;
+                    VersionCheck::Apply
+                }
+                Some(version) if update.version <= *version => VersionCheck::Ignore,
+                // Updates sent before the server handled the unsubscribe request
+                None if self.unsubscribed.contains(&update.name) => VersionCheck::Ignore,
+                _ => {
+                    self.resyncing.insert(update.name.clone());
+                    VersionCheck::Resync
+                }
+            },
+            // Batches are split into their messages before being checked
+            ServerSignalMessage::Batch(_) => VersionCheck::Apply,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use json_patch::Patch;
+
+    use super::*;
+    use crate::{ServerSignalSnapshot, ServerSignalUpdate};
+
+    fn snapshot(version: u64) -> ServerSignalMessage {
+        ServerSignalSnapshot::new_from_json("count", serde_json::json!(0))
+            .with_version(version)
+            .into()
+    }
+
+    fn update(version: u64) -> ServerSignalMessage {
+        ServerSignalUpdate::new_from_patch("count", Patch(Vec::new()))
+            .with_version(version)
+            .into()
+    }
+
+    #[test]
+    fn consecutive_updates_are_applied() {
+        let mut versions = SignalVersions::default();
+        assert_eq!(versions.check(&snapshot(3)), VersionCheck::Apply);
+        assert_eq!(versions.check(&update(4)), VersionCheck::Apply);
+        assert_eq!(versions.check(&update(5)), VersionCheck::Apply);
+        assert_eq!(versions.versions["count"], 5);
+    }
+
+    #[test]
+    fn duplicate_and_stale_updates_are_ignored() {
+        let mut versions = SignalVersions::default();
+        versions.check(&snapshot(3));
+        versions.check(&update(4));
+        assert_eq!(versions.check(&update(4)), VersionCheck::Ignore);
+        assert_eq!(versions.check(&update(2)), VersionCheck::Ignore);
+        assert_eq!(versions.versions["count"], 4);
+        assert_eq!(versions.check(&update(5)), VersionCheck::Apply);
+    }
+
+    #[test]
+    fn gap_requests_resync_until_snapshot() {
+        let mut versions = SignalVersions::default();
+        versions.check(&snapshot(3));
+        assert_eq!(versions.check(&update(5)), VersionCheck::Resync);
+        assert!(versions.resyncing.contains("count"));
+
+        // Updates are ignored while waiting for the snapshot
+        assert_eq!(versions.check(&update(6)), VersionCheck::Ignore);
+        assert_eq!(versions.check(&update(4)), VersionCheck::Ignore);
+
+        assert_eq!(versions.check(&snapshot(6)), VersionCheck::Apply);
+        assert!(versions.resyncing.is_empty());
+        assert_eq!(versions.check(&update(7)), VersionCheck::Apply);
+    }
+
+    #[test]
+    fn update_before_snapshot_requests_resync() {
+        let mut versions = SignalVersions::default();
+        assert_eq!(versions.check(&update(1)), VersionCheck::Resync);
+    }
+
+    #[test]
+    fn older_snapshot_is_applied() {
+        let mut versions = SignalVersions::default();
+        versions.check(&snapshot(5));
+        // The server restarted, so its versions start again
+        assert_eq!(versions.check(&snapshot(0)), VersionCheck::Apply);
+        assert_eq!(versions.check(&update(1)), VersionCheck::Apply);
+    }
+
+    #[test]
+    fn updates_to_unsubscribed_signals_are_ignored() {
+        let mut versions = SignalVersions::default();
+        versions.unsubscribed.insert("count".into());
+        assert_eq!(versions.check(&update(8)), VersionCheck::Ignore);
+        assert!(versions.resyncing.is_empty());
+    }
+
+    #[test]
+    fn long_poll_request_leaves_out_resyncing_signals() {
+        let mut versions = SignalVersions::default();
+        versions.versions.insert("count".into(), 3);
+        versions.versions.insert("total".into(), 7);
+        versions.resyncing.insert("total".into());
+
+        let request = versions.long_poll_request();
+        assert_eq!(request.version("count"), Some(3));
+        assert_eq!(request.version("total"), None);
+    }
+}
@@ -0,0 +1,73 @@
+#![cfg(feature = "derive")]
+
+use json_patch::Patch;
+use leptos_server_signal::Diff;
+use serde::Serialize;
+
+#[derive(Clone, Default, PartialEq, Serialize, Diff)]
+struct State {
+    count: i32,
+    #[serde(rename = "items")]
+    messages: Vec<String>,
+    #[serde(skip)]
+    cache: Vec<i32>,
+    #[diff(nested)]
+    settings: Settings,
+}
+
+#[derive(Clone, Default, PartialEq, Serialize, Diff)]
+struct Settings {
+    theme: String,
+    #[serde(rename = "font~size/px")]
+    font_size: u32,
+}
+
+/// Applies the derived diff to the old json value, asserting it produces the new json value.
+fn assert_diff_matches<T>(old: &T, new: &T) -> Patch
+where
+    T: Diff + Serialize,
+{
+    let mut patch = Patch(Vec::new());
+    new.diff(old, "", &mut patch).unwrap();
+    let mut json = serde_json::to_value(old).unwrap();
+    json_patch::patch(&mut json, &patch).unwrap();
+    assert_eq!(json, serde_json::to_value(new).unwrap());
+    patch
+}
+
+#[test]
+fn unchanged_value_has_empty_diff() {
+    let state = State::default();
+    assert!(assert_diff_matches(&state, &state.clone()).0.is_empty());
+}
+
+#[test]
+fn changed_fields_match_serialization() {
+    let old = State::default();
+    let new = State {
+        count: 1,
+        messages: vec!["hello".to_string()],
+        cache: vec![1, 2, 3],
+        settings: Settings {
+            theme: "dark".to_string(),
+            font_size: 14,
+        },
+    };
+    // One operation for each changed field, none for the skipped cache
+    assert_eq!(assert_diff_matches(&old, &new).0.len(), 4);
+}
+
+#[test]
+fn nested_fields_are_diffed_individually() {
+    let old = State::default();
+    let new = State {
+        settings: Settings {
+            font_size: 12,
+            ..Settings::default()
+        },
+        ..State::default()
+    };
+    let patch = assert_diff_matches(&old, &new);
+    assert_eq!(patch.0.len(), 1);
+    assert_eq!(patch.0[0].path().to_string(), "/settings/font~0size~1px");
+}